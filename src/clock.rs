@@ -0,0 +1,47 @@
+use std::cell::Cell;
+use std::time::Instant;
+use std::time::Duration;
+
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+pub struct RealClock;
+
+impl Clock for RealClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+// Clock that only moves when it's told to
+pub struct MockClock {
+    now: Cell<Instant>,
+}
+
+impl MockClock {
+    pub fn new() -> Self {
+        MockClock { now: Cell::new(Instant::now()) }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+
+    pub fn advance_to(&self, time: Instant) {
+        assert!(time >= self.now.get(), "a clock can't go backwards");
+        self.now.set(time);
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.now.get()
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        MockClock::new()
+    }
+}
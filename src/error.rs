@@ -0,0 +1,65 @@
+use std::fmt;
+use std::error::Error;
+
+use glium::backend::glutin::DisplayCreationError;
+use glium::ProgramCreationError;
+use glium::SwapBuffersError;
+use glium::texture::TextureCreationError;
+
+#[derive(Debug)]
+pub enum EngineError {
+    ContextCreation(DisplayCreationError),
+    ProgramCreation(ProgramCreationError),
+    // The font library's error doesn't implement Error, so its description is kept
+    FontCreation(Box<dyn Error + Send + Sync>),
+    TextureCreation(TextureCreationError),
+    SwapBuffers(SwapBuffersError),
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EngineError::ContextCreation(e) => write!(f, "failed to create the OpenGL context: {}", e),
+            EngineError::ProgramCreation(e) => write!(f, "failed to compile or link a shader program: {}", e),
+            EngineError::FontCreation(e) => write!(f, "failed to load the font: {}", e),
+            EngineError::TextureCreation(e) => write!(f, "failed to upload a texture: {}", e),
+            EngineError::SwapBuffers(e) => write!(f, "failed to swap buffers: {}", e),
+        }
+    }
+}
+
+impl Error for EngineError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EngineError::ContextCreation(e) => Some(e),
+            EngineError::ProgramCreation(e) => Some(e),
+            EngineError::FontCreation(e) => Some(&**e),
+            EngineError::TextureCreation(e) => Some(e),
+            EngineError::SwapBuffers(e) => Some(e),
+        }
+    }
+}
+
+impl From<DisplayCreationError> for EngineError {
+    fn from(e: DisplayCreationError) -> Self {
+        EngineError::ContextCreation(e)
+    }
+}
+
+impl From<ProgramCreationError> for EngineError {
+    fn from(e: ProgramCreationError) -> Self {
+        EngineError::ProgramCreation(e)
+    }
+}
+
+impl From<TextureCreationError> for EngineError {
+    fn from(e: TextureCreationError) -> Self {
+        EngineError::TextureCreation(e)
+    }
+}
+
+impl From<SwapBuffersError> for EngineError {
+    fn from(e: SwapBuffersError) -> Self {
+        EngineError::SwapBuffers(e)
+    }
+}
@@ -1,8 +1,14 @@
+use std::fmt;
+use std::error::Error;
 use std::ops::Deref;
 
 use glium::Display;
+use glium::Vertex;
+use glium::VertexBuffer;
+use glium::IndexBuffer;
 use glium::index::PrimitiveType;
 use glium::texture::RawImage2d;
+use glium::texture::TextureCreationError;
 use glium::texture::texture2d::Texture2d;
 
 use cgmath::Vector3;
@@ -18,6 +24,48 @@ use super::TexturedSceneObject3d;
 use super::TexturelessSceneObject2d;
 use super::TexturedSceneObject2d;
 
+#[derive(Debug)]
+pub enum SceneObjectCreationError {
+    IndexOutOfRange { index: u32, num_vertices: usize },
+    IncompleteTriangle { num_indices: usize },
+    VertexBufferCreation(glium::vertex::BufferCreationError),
+    IndexBufferCreation(glium::index::BufferCreationError),
+    TextureCreation(TextureCreationError),
+}
+
+impl fmt::Display for SceneObjectCreationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneObjectCreationError::IndexOutOfRange { index, num_vertices } => {
+                write!(f, "index {} is out of range for {} vertices", index, num_vertices)
+            },
+            SceneObjectCreationError::IncompleteTriangle { num_indices } => {
+                write!(f, "{} indices don't make up whole triangles", num_indices)
+            },
+            SceneObjectCreationError::VertexBufferCreation(e) => {
+                write!(f, "failed to create the vertex buffer: {}", e)
+            },
+            SceneObjectCreationError::IndexBufferCreation(e) => {
+                write!(f, "failed to create the index buffer: {}", e)
+            },
+            SceneObjectCreationError::TextureCreation(e) => {
+                write!(f, "failed to create the texture: {}", e)
+            },
+        }
+    }
+}
+
+impl Error for SceneObjectCreationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SceneObjectCreationError::VertexBufferCreation(e) => Some(e),
+            SceneObjectCreationError::IndexBufferCreation(e) => Some(e),
+            SceneObjectCreationError::TextureCreation(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
 pub struct SceneObjectCreator<'a> {
     display: &'a Display,
     buffer3d: Vec<Vertex3d>,
@@ -34,12 +82,19 @@ impl<'a> SceneObjectCreator<'a> {
     }
 
     pub fn create_texture(&mut self, image: DynamicImage) -> Texture2d {
+        self.try_create_texture(image).unwrap()
+    }
+
+    pub fn try_create_texture(
+        &mut self,
+        image: DynamicImage,
+    ) -> Result<Texture2d, SceneObjectCreationError> {
         let image_dimensions = image.dimensions();
         let raw_image = RawImage2d::from_raw_rgba_reversed(
             &image.to_rgba().into_raw(),
             image_dimensions
         );
-        Texture2d::new(self.display, raw_image).unwrap()
+        Texture2d::new(self.display, raw_image).map_err(SceneObjectCreationError::TextureCreation)
     }
 
     pub fn create_textureless3d(
@@ -47,22 +102,27 @@ impl<'a> SceneObjectCreator<'a> {
         vertices: &[Vector3<f32>],
         indices: &[u32],
     ) -> TexturelessSceneObject3d {
+        self.try_create_textureless3d(vertices, indices).unwrap()
+    }
+
+    pub fn try_create_textureless3d(
+        &mut self,
+        vertices: &[Vector3<f32>],
+        indices: &[u32],
+    ) -> Result<TexturelessSceneObject3d, SceneObjectCreationError> {
         self.buffer3d.clear();
         self.buffer3d.extend(vertices.iter().map(|v| Vertex3d {
             position: (*v).into(),
             texture_position: [0.0, 0.0],
         }));
-        let vertex_buffer = glium::VertexBuffer::new(self.display, &self.buffer3d).unwrap();
-        let index_buffer = glium::IndexBuffer::new(
-            self.display,
-            PrimitiveType::TrianglesList,
-            indices
-        ).unwrap();
-
-        TexturelessSceneObject3d {
+        let (vertex_buffer, index_buffer) = create_buffers(self.display, &self.buffer3d, indices)?;
+
+        Ok(TexturelessSceneObject3d {
             vertex_buffer,
             index_buffer,
-        }
+            positions: vertices.to_vec(),
+            indices: indices.to_vec(),
+        })
     }
 
     pub fn create_textured3d<T: Deref<Target = Texture2d>>(
@@ -71,23 +131,29 @@ impl<'a> SceneObjectCreator<'a> {
         indices: &[u32],
         texture: T,
     ) -> TexturedSceneObject3d<T> {
+        self.try_create_textured3d(vertices, indices, texture).unwrap()
+    }
+
+    pub fn try_create_textured3d<T: Deref<Target = Texture2d>>(
+        &mut self,
+        vertices: &[(Vector3<f32>, Vector2<f32>)],
+        indices: &[u32],
+        texture: T,
+    ) -> Result<TexturedSceneObject3d<T>, SceneObjectCreationError> {
         self.buffer3d.clear();
         self.buffer3d.extend(vertices.iter().map(|v| Vertex3d {
             position: v.0.into(),
             texture_position: v.1.into(),
         }));
-        let vertex_buffer = glium::VertexBuffer::new(self.display, &self.buffer3d).unwrap();
-        let index_buffer = glium::IndexBuffer::new(
-            self.display,
-            PrimitiveType::TrianglesList,
-            indices
-        ).unwrap();
-
-        TexturedSceneObject3d {
+        let (vertex_buffer, index_buffer) = create_buffers(self.display, &self.buffer3d, indices)?;
+
+        Ok(TexturedSceneObject3d {
             vertex_buffer,
             index_buffer,
             texture,
-        }
+            positions: vertices.iter().map(|v| v.0).collect(),
+            indices: indices.to_vec(),
+        })
     }
 
     pub fn create_textureless2d(
@@ -95,22 +161,25 @@ impl<'a> SceneObjectCreator<'a> {
         vertices: &[Vector2<f32>],
         indices: &[u32],
     ) -> TexturelessSceneObject2d {
+        self.try_create_textureless2d(vertices, indices).unwrap()
+    }
+
+    pub fn try_create_textureless2d(
+        &mut self,
+        vertices: &[Vector2<f32>],
+        indices: &[u32],
+    ) -> Result<TexturelessSceneObject2d, SceneObjectCreationError> {
         self.buffer2d.clear();
         self.buffer2d.extend(vertices.iter().map(|v| Vertex2d {
             position: (*v).into(),
             texture_position: [0.0, 0.0],
         }));
-        let vertex_buffer = glium::VertexBuffer::new(self.display, &self.buffer2d).unwrap();
-        let index_buffer = glium::IndexBuffer::new(
-            self.display,
-            PrimitiveType::TrianglesList,
-            indices
-        ).unwrap();
-
-        TexturelessSceneObject2d {
+        let (vertex_buffer, index_buffer) = create_buffers(self.display, &self.buffer2d, indices)?;
+
+        Ok(TexturelessSceneObject2d {
             vertex_buffer,
             index_buffer,
-        }
+        })
     }
 
     pub fn create_textured2d<T: Deref<Target = Texture2d>>(
@@ -119,22 +188,52 @@ impl<'a> SceneObjectCreator<'a> {
         indices: &[u32],
         texture: T,
     ) -> TexturedSceneObject2d<T> {
+        self.try_create_textured2d(vertices, indices, texture).unwrap()
+    }
+
+    pub fn try_create_textured2d<T: Deref<Target = Texture2d>>(
+        &mut self,
+        vertices: &[(Vector2<f32>, Vector2<f32>)],
+        indices: &[u32],
+        texture: T,
+    ) -> Result<TexturedSceneObject2d<T>, SceneObjectCreationError> {
         self.buffer2d.clear();
         self.buffer2d.extend(vertices.iter().map(|v| Vertex2d {
             position: [v.0.x, v.0.y],
             texture_position: v.1.into(),
         }));
-        let vertex_buffer = glium::VertexBuffer::new(self.display, &self.buffer2d).unwrap();
-        let index_buffer = glium::IndexBuffer::new(
-            self.display,
-            PrimitiveType::TrianglesList,
-            indices
-        ).unwrap();
-
-        TexturedSceneObject2d {
+        let (vertex_buffer, index_buffer) = create_buffers(self.display, &self.buffer2d, indices)?;
+
+        Ok(TexturedSceneObject2d {
             vertex_buffer,
             index_buffer,
             texture,
-        }
+        })
+    }
+}
+
+fn create_buffers<V: Vertex>(
+    display: &Display,
+    vertices: &[V],
+    indices: &[u32],
+) -> Result<(VertexBuffer<V>, IndexBuffer<u32>), SceneObjectCreationError> {
+    validate_indices(indices, vertices.len())?;
+    let vertex_buffer = VertexBuffer::new(display, vertices)
+        .map_err(SceneObjectCreationError::VertexBufferCreation)?;
+    let index_buffer = IndexBuffer::new(display, PrimitiveType::TrianglesList, indices)
+        .map_err(SceneObjectCreationError::IndexBufferCreation)?;
+    Ok((vertex_buffer, index_buffer))
+}
+
+pub(crate) fn validate_indices(
+    indices: &[u32],
+    num_vertices: usize,
+) -> Result<(), SceneObjectCreationError> {
+    if indices.len() % 3 != 0 {
+        return Err(SceneObjectCreationError::IncompleteTriangle { num_indices: indices.len() });
+    }
+    match indices.iter().find(|&&index| index as usize >= num_vertices) {
+        Some(&index) => Err(SceneObjectCreationError::IndexOutOfRange { index, num_vertices }),
+        None => Ok(()),
     }
 }
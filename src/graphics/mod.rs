@@ -1,6 +1,7 @@
 pub mod render;
 pub mod create;
 pub mod color;
+pub mod pick;
 
 use std::ops::Deref;
 
@@ -9,21 +10,30 @@ use glium::DrawParameters;
 use glium::Blend;
 use glium::Program;
 use glium::Depth;
+use glium::Surface;
 use glium::glutin::dpi::LogicalSize;
 use glium::implement_vertex;
 use glium::VertexBuffer;
 use glium::IndexBuffer;
 use glium::texture::texture2d::Texture2d;
 
+use cgmath::Vector3;
+use cgmath::Vector2;
+use cgmath::Matrix3;
+
 use glium_text::TextSystem;
 use glium_text::TextDisplay;
 use glium_text::FontTexture;
 
 use crate::GameInfo;
 use crate::GraphicsInfo;
+use crate::EngineError;
+use crate::CursorSprite;
 
 use self::create::SceneObjectCreator;
 use self::render::SceneRenderer;
+use self::render::OverlayRenderer;
+use self::render::OverlayAlignment;
 
 const WORLD_VERTEX_SHADER_SOURCE: &'static str = include_str!("../../shader_src/world/vertex_shader.vert");
 const WORLD_FRAGMENT_SHADER_SOURCE: &'static str = include_str!("../../shader_src/world/fragment_shader.frag");
@@ -52,12 +62,16 @@ implement_vertex!(Vertex2d, position, texture_position);
 pub struct TexturelessSceneObject3d {
     vertex_buffer: VertexBuffer<Vertex3d>,
     index_buffer: IndexBuffer<u32>,
+    positions: Vec<Vector3<f32>>, // CPU-side copy of the geometry for picking
+    indices: Vec<u32>,
 }
 
 pub struct TexturedSceneObject3d<T: Deref<Target = Texture2d>> {
     vertex_buffer: VertexBuffer<Vertex3d>,
     index_buffer: IndexBuffer<u32>,
     texture: T,
+    positions: Vec<Vector3<f32>>, // CPU-side copy of the geometry for picking
+    indices: Vec<u32>,
 }
 
 pub struct TexturelessSceneObject2d {
@@ -83,21 +97,21 @@ pub struct Graphics {
 }
 
 impl Graphics {
-    pub fn new(display: &Display, mut optimal_window_size: LogicalSize) -> Self {
+    pub fn new(display: &Display, mut optimal_window_size: LogicalSize) -> Result<Self, EngineError> {
         // load shader sources and create programs
         let world_program = glium::Program::from_source(
             display,
             WORLD_VERTEX_SHADER_SOURCE,
             WORLD_FRAGMENT_SHADER_SOURCE,
             Some(WORLD_GEOMETRY_SHADER_SOURCE),
-        ).unwrap();
+        )?;
 
         let overlay_program = glium::Program::from_source(
             display,
             OVERLAY_VERTEX_SHADER_SOURCE,
             OVERLAY_FRAGMENT_SHADER_SOURCE,
             Some(OVERLAY_GEOMETRY_SHADER_SOURCE),
-        ).unwrap();
+        )?;
 
         // create draw parameters
         let draw_parameters = DrawParameters {
@@ -117,15 +131,16 @@ impl Graphics {
 
         // load font and create text system
         let font_file = include_bytes!("../../font/DejaVuSansMono.ttf");
-        let font = FontTexture::new(display, font_file.as_ref(), TEXT_FONT_SIZE).unwrap();
+        let font = FontTexture::new(display, font_file.as_ref(), TEXT_FONT_SIZE)
+            .map_err(|e| EngineError::FontCreation(format!("{:?}", e).into()))?;
         let text_system = TextSystem::new(display);
         let text_display = TextDisplay::new(&text_system, Box::new(font), "");
 
         // create an empty texture
-        let white_texture = Texture2d::new(display, vec![vec![(1.0, 1.0, 1.0, 1.0)]]).unwrap();
+        let white_texture = Texture2d::new(display, vec![vec![(1.0, 1.0, 1.0, 1.0)]])?;
 
         // create the graphics
-        Graphics {
+        Ok(Graphics {
             world_program,
             overlay_program,
             draw_parameters,
@@ -134,7 +149,7 @@ impl Graphics {
             text_system,
             text_display,
             white_texture,
-        }
+        })
     }
 
     pub fn render<A: super::Application>(
@@ -143,7 +158,8 @@ impl Graphics {
         display: &Display,
         game_info: Option<GameInfo<A::G>>,
         graphics_info: GraphicsInfo,
-    ) {
+        cursor: Option<(&CursorSprite, Vector2<f64>)>,
+    ) -> Result<(), EngineError> {
         // create new frame
         let mut frame = display.draw();
 
@@ -163,8 +179,30 @@ impl Graphics {
         // let the game render the scene via the renderer
         application.render(game_info, graphics_info, scene_renderer);
 
+        // draw the cursor sprite on top of everything, its position is in overlay coordinates
+        if let Some((sprite, position)) = cursor {
+            frame.clear_depth(1.0);
+            let mut overlay_renderer = OverlayRenderer::new(
+                self.screen_ratio,
+                self.optimal_window_size,
+                &mut frame,
+                &self.text_system,
+                &mut self.text_display,
+                &self.overlay_program,
+                &self.draw_parameters,
+                &self.white_texture,
+            );
+            let offset = Vector2::new(position.x as f32, position.y as f32) - sprite.hotspot;
+            overlay_renderer.draw_textured(
+                &*sprite.object,
+                &Matrix3::from_translation(offset),
+                OverlayAlignment::Center,
+            );
+        }
+
         // swap buffers
-        frame.finish().unwrap(); // TODO maybe not unwrap?
+        frame.finish()?;
+        Ok(())
     }
 
     pub fn set_view_port_size(&mut self, size: LogicalSize) {
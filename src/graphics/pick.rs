@@ -0,0 +1,105 @@
+use std::ops::Deref;
+
+use glium::texture::texture2d::Texture2d;
+
+use cgmath::Matrix4;
+use cgmath::SquareMatrix;
+use cgmath::InnerSpace;
+use cgmath::Vector3;
+use cgmath::Vector4;
+
+use super::TexturelessSceneObject3d;
+use super::TexturedSceneObject3d;
+
+const EPSILON: f32 = 1e-7;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Vector3<f32>,
+    pub direction: Vector3<f32>, // Normalized when created by a camera
+}
+
+impl Ray {
+    pub fn point_at(&self, distance: f32) -> Vector3<f32> {
+        self.origin + self.direction * distance
+    }
+
+    // the ray in the space of an object that is drawn with the matrix
+    fn to_object_space(&self, object_to_world_matrix: &Matrix4<f32>) -> Option<Ray> {
+        let world_to_object_matrix = object_to_world_matrix.invert()?;
+        Some(Ray {
+            origin: (world_to_object_matrix * self.origin.extend(1.0)).truncate(),
+            direction: (world_to_object_matrix * self.direction.extend(0.0)).truncate(),
+        })
+    }
+}
+
+// Unprojects a point in normalized device coordinates with the inverse of a world to screen matrix
+pub(crate) fn unproject(world_to_screen_matrix: Matrix4<f32>, x: f32, y: f32) -> Option<Ray> {
+    let screen_to_world_matrix = world_to_screen_matrix.invert()?;
+    let near = screen_to_world_matrix * Vector4::new(x, y, -1.0, 1.0);
+    let far = screen_to_world_matrix * Vector4::new(x, y, 1.0, 1.0);
+    let near = near.truncate() / near.w;
+    let far = far.truncate() / far.w;
+    Some(Ray {
+        origin: near,
+        direction: (far - near).normalize(),
+    })
+}
+
+// Distance along the ray to the nearest triangle it hits, both sides of a triangle count
+pub(crate) fn intersect_triangles(ray: &Ray, positions: &[Vector3<f32>], indices: &[u32]) -> Option<f32> {
+    indices.chunks(3)
+        .filter_map(|triangle| {
+            let a = positions[triangle[0] as usize];
+            let b = positions[triangle[1] as usize];
+            let c = positions[triangle[2] as usize];
+            intersect_triangle(ray, a, b, c)
+        })
+        .fold(None, |nearest: Option<f32>, distance| {
+            Some(nearest.map_or(distance, |n| n.min(distance)))
+        })
+}
+
+// Möller-Trumbore
+fn intersect_triangle(ray: &Ray, a: Vector3<f32>, b: Vector3<f32>, c: Vector3<f32>) -> Option<f32> {
+    let edge1 = b - a;
+    let edge2 = c - a;
+    let p = ray.direction.cross(edge2);
+    let determinant = edge1.dot(p);
+    if determinant.abs() < EPSILON {
+        return None;
+    }
+    let inverse_determinant = 1.0 / determinant;
+    let s = ray.origin - a;
+    let u = s.dot(p) * inverse_determinant;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(edge1);
+    let v = ray.direction.dot(q) * inverse_determinant;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let distance = edge2.dot(q) * inverse_determinant;
+    if distance < 0.0 {
+        return None;
+    }
+    Some(distance)
+}
+
+impl TexturelessSceneObject3d {
+    // Distance along the ray to the object when it's drawn with the matrix
+    pub fn intersect(&self, ray: &Ray, object_to_world_matrix: &Matrix4<f32>) -> Option<f32> {
+        let ray = ray.to_object_space(object_to_world_matrix)?;
+        intersect_triangles(&ray, &self.positions, &self.indices)
+    }
+}
+
+impl<T: Deref<Target = Texture2d>> TexturedSceneObject3d<T> {
+    // Distance along the ray to the object when it's drawn with the matrix
+    pub fn intersect(&self, ray: &Ray, object_to_world_matrix: &Matrix4<f32>) -> Option<f32> {
+        let ray = ray.to_object_space(object_to_world_matrix)?;
+        intersect_triangles(&ray, &self.positions, &self.indices)
+    }
+}
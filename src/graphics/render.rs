@@ -30,6 +30,8 @@ use super::TexturedSceneObject2d;
 use super::LogicalSize;
 use super::Vertex3d;
 use super::Vertex2d;
+use super::pick::Ray;
+use super::pick::unproject;
 
 pub const TEXT_NUM_LINES: u64 = 50; // Number of text lines that cover the whole vertical on the screen
 const TEXT_MARGIN: f64 = 0.2; // Line height relative space between lines and to the screen borders,
@@ -107,6 +109,18 @@ impl Camera {
     pub fn as_matrix(&self, screen_ratio: f64, optimal_screen_ratio: f64) -> Matrix4<f32> {
         self.projection.as_matrix(screen_ratio, optimal_screen_ratio) * self.translation_rotation
     }
+
+    // The world space ray through a point in normalized device coordinates,
+    // e.g. the cursor position, starting at the near plane
+    pub fn ray(
+        &self,
+        position: Vector2<f64>,
+        screen_ratio: f64,
+        optimal_screen_ratio: f64,
+    ) -> Option<Ray> {
+        let world_to_screen_matrix = self.as_matrix(screen_ratio, optimal_screen_ratio);
+        unproject(world_to_screen_matrix, position.x as f32, position.y as f32)
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -139,6 +153,66 @@ impl OverlayAlignment {
     }
 }
 
+// Maps overlay coordinates to normalized device coordinates and back.
+// The overlay covers the largest area with the ratio of the optimal window size that fits the screen,
+// the alignment decides where that area is placed.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct OverlayTransform {
+    scaling: Vector2<f64>,
+    offset_base: Vector2<f64>,
+}
+
+impl OverlayTransform {
+    pub(crate) fn new(screen_ratio: f64, optimal_window_size: LogicalSize) -> Self {
+        let ratio_ratio = screen_ratio / optimal_window_size.width * optimal_window_size.height;
+        if ratio_ratio > 1.0 {
+            OverlayTransform {
+                scaling: Vector2::new(
+                    2.0 / ratio_ratio / optimal_window_size.width,
+                    2.0 / optimal_window_size.height,
+                ),
+                offset_base: Vector2::new(1.0 - 1.0 / ratio_ratio, 0.0),
+            }
+        } else {
+            OverlayTransform {
+                scaling: Vector2::new(
+                    2.0 / optimal_window_size.width,
+                    2.0 * ratio_ratio / optimal_window_size.height,
+                ),
+                offset_base: Vector2::new(0.0, 1.0 - ratio_ratio),
+            }
+        }
+    }
+
+    pub(crate) fn scaling(&self) -> Vector2<f64> {
+        self.scaling
+    }
+
+    pub(crate) fn offset(&self, alignment: OverlayAlignment) -> Vector2<f64> {
+        let offset_factor = alignment.offset_factor();
+        Vector2::new(
+            self.offset_base.x * offset_factor.x - 1.0,
+            self.offset_base.y * offset_factor.y - 1.0,
+        )
+    }
+
+    pub(crate) fn overlay_to_ndc(&self, position: Vector2<f64>, alignment: OverlayAlignment) -> Vector2<f64> {
+        let offset = self.offset(alignment);
+        Vector2::new(
+            position.x * self.scaling.x + offset.x,
+            position.y * self.scaling.y + offset.y,
+        )
+    }
+
+    pub(crate) fn ndc_to_overlay(&self, position: Vector2<f64>, alignment: OverlayAlignment) -> Vector2<f64> {
+        let offset = self.offset(alignment);
+        Vector2::new(
+            (position.x - offset.x) / self.scaling.x,
+            (position.y - offset.y) / self.scaling.y,
+        )
+    }
+}
+
 // TODO make colors own type (maybe use another crate?)
 #[derive(Clone)]
 pub struct SceneSettings {
@@ -341,14 +415,13 @@ pub struct OverlayRenderer<'a> {
     program: &'a Program,
     draw_parameters: &'a DrawParameters<'a>,
     white_texture: &'a Texture2d,
-    scaling: Vector2<f64>,
-    offset_base: Vector2<f64>,
+    transform: OverlayTransform,
     text_system: &'a TextSystem,
     text_display: &'a mut TextDisplay<Box<FontTexture>>,
 }
 
 impl<'a> OverlayRenderer<'a> {
-    fn new(
+    pub(crate) fn new(
         screen_ratio: f64,
         optimal_window_size: LogicalSize,
         frame: &'a mut Frame,
@@ -358,27 +431,9 @@ impl<'a> OverlayRenderer<'a> {
         draw_parameters: &'a DrawParameters<'a>,
         white_texture: &'a Texture2d,
     ) -> OverlayRenderer<'a> {
-        let ratio_ratio = screen_ratio / optimal_window_size.width * optimal_window_size.height;
-        let scaling;
-        let offset_base;
-        if ratio_ratio > 1.0 {
-            scaling = Vector2::new(
-                2.0 / ratio_ratio / optimal_window_size.width,
-                2.0 / optimal_window_size.height,
-            );
-            offset_base = Vector2::new(1.0 - 1.0 / ratio_ratio, 0.0);
-        } else {
-            scaling = Vector2::new(
-                2.0 / optimal_window_size.width,
-                2.0 * ratio_ratio / optimal_window_size.height,
-            );
-            offset_base = Vector2::new(0.0, 1.0 - ratio_ratio);
-        }
-
         OverlayRenderer {
             frame,
-            scaling,
-            offset_base,
+            transform: OverlayTransform::new(screen_ratio, optimal_window_size),
             text_system,
             text_display,
             program,
@@ -482,11 +537,12 @@ impl<'a> OverlayRenderer<'a> {
         object_to_overlay_matrix: &Matrix3<f32>,
         alignment: OverlayAlignment,
     ) {
-        let offset_factor = alignment.offset_factor();
-        let x_offset = (self.offset_base.x * offset_factor.x - 1.0) as f32;
-        let y_offset = (self.offset_base.y * offset_factor.y - 1.0) as f32;
-        let x_scaling = self.scaling.x as f32;
-        let y_scaling = self.scaling.y as f32;
+        let offset = self.transform.offset(alignment);
+        let scaling = self.transform.scaling();
+        let x_offset = offset.x as f32;
+        let y_offset = offset.y as f32;
+        let x_scaling = scaling.x as f32;
+        let y_scaling = scaling.y as f32;
         let overlay_to_screen_matrix = Matrix3::new(
             x_scaling, 0.0,       0.0,
             0.0,       y_scaling, 0.0,
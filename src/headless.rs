@@ -0,0 +1,122 @@
+use std::time::Instant;
+use std::time::Duration;
+
+use crate::Clock;
+use crate::MockClock;
+use crate::Application;
+use crate::Game;
+use crate::ApplicationData;
+use crate::Event;
+use crate::GameInfo;
+use crate::GameController;
+use crate::ControlsController;
+
+// Drives an application and its game without a window, using a virtual clock
+pub struct HeadlessRunner<A: Application> {
+    application_data: ApplicationData<A>,
+    clock: MockClock,
+}
+
+impl<A: Application> HeadlessRunner<A> {
+    pub fn new(application: A) -> Self {
+        HeadlessRunner {
+            application_data: ApplicationData::new(application, Vec::new()),
+            clock: MockClock::new(),
+        }
+    }
+
+    pub fn clock(&self) -> &MockClock {
+        &self.clock
+    }
+
+    pub fn now(&self) -> Instant {
+        self.clock.now()
+    }
+
+    pub fn application(&self) -> &A {
+        &self.application_data.application
+    }
+
+    pub fn game_info(&self) -> Option<GameInfo<A::G>> {
+        let now = self.clock.now();
+        self.application_data.game_data.as_ref().map(|gd| gd.game_info(now))
+    }
+
+    pub fn game_controller(&mut self) -> GameController<A::G> {
+        GameController::new(
+            &mut self.application_data.game_data,
+            &mut self.application_data.game_notifications,
+            self.clock.now(),
+        )
+    }
+
+    // there are no devices without a window, so nothing is ever captured
+    pub fn controls_controller(&mut self) -> ControlsController<A> {
+        ControlsController {
+            controls_data: &mut self.application_data.controls_data,
+        }
+    }
+
+    pub fn next_update_time(&self) -> Option<Instant> {
+        self.application_data.game_data.as_ref().and_then(|gd| gd.next_update_time())
+    }
+
+    pub fn closing(&self) -> bool {
+        self.application_data.closing
+    }
+
+    pub fn emit_event(
+        &mut self,
+        event: Event<A::FireTarget, A::SwitchTarget, A::ValueTarget, <A::G as Game>::Event>,
+    ) {
+        self.application_data.dispatch_game_notifications(self.clock.now(), None);
+        self.application_data.emit_event(event, self.clock.now(), None);
+    }
+
+    // Types a character, which is reported while text input is enabled
+    pub fn receive_character(&mut self, c: char) {
+        self.application_data.dispatch_game_notifications(self.clock.now(), None);
+        self.application_data.receive_character(c, self.clock.now(), None);
+    }
+
+    pub fn advance(&mut self, duration: Duration) {
+        let target_time = self.clock.now() + duration;
+        // the events after advancing belong to a later batch than the ones before
+        self.application_data.start_event_batch();
+        self.application_data.dispatch_game_notifications(self.clock.now(), None);
+        // a paused replay only advances through its control events
+        self.application_data.emit_replayed_control_events(self.clock.now(), None);
+
+        // run every update that is due until the target time, each at its scheduled time
+        while !self.application_data.closing {
+            match self.next_update_time() {
+                Some(nut) if nut <= target_time => {
+                    self.clock.advance_to(nut.max(self.clock.now()));
+                    self.application_data.maybe_update_game(self.clock.now(), None);
+                    // a game in turbo mode is always due, it gets one iteration per advance
+                    if self.application_data.game_turbo() {
+                        break;
+                    }
+                },
+                _ => break,
+            }
+        }
+        self.clock.advance_to(target_time);
+    }
+
+    // Emits each event after advancing by the duration it is paired with
+    // and returns the game that is left after the last one
+    pub fn run<I>(mut self, script: I) -> Option<A::G>
+    where I: IntoIterator<
+        Item = (Duration, Event<A::FireTarget, A::SwitchTarget, A::ValueTarget, <A::G as Game>::Event>)
+    > {
+        for (duration, event) in script {
+            self.advance(duration);
+            if self.application_data.closing {
+                break;
+            }
+            self.emit_event(event);
+        }
+        self.application_data.game_data.map(|gd| gd.game)
+    }
+}
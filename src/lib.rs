@@ -1,21 +1,47 @@
 mod graphics;
-
+mod headless;
+mod clock;
+mod record;
+mod save;
+mod rewind;
+mod error;
+
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::mem;
+use std::ops::Deref;
+use std::ops::DerefMut;
+use std::convert::Infallible;
 use std::time::Instant;
 use std::time::Duration;
 use std::hash::Hash;
 use std::str::FromStr;
 use std::collections::vec_deque::VecDeque;
+use std::rc::Rc;
 
 use glium::glutin::event::Event as WinitEvent;
 use glium::glutin::event_loop::EventLoop;
 use glium::glutin::event::WindowEvent;
+use glium::glutin::event::KeyboardInput;
+use glium::glutin::event::ElementState;
+use glium::glutin::event::DeviceEvent;
+use glium::glutin::event::DeviceId;
 use glium::glutin::event_loop::ControlFlow;
 use glium::glutin::window::Window;
 use glium::glutin::dpi::LogicalPosition;
 use glium::Display;
+use cgmath::Vector2;
 
 use controls::Controls;
 use self::graphics::Graphics;
+use self::graphics::render::OverlayTransform;
+use self::record::Recorder;
+use self::record::Replay;
+use self::record::control_event_to_string;
+use self::record::parse_control_event;
+use self::save::SaveHeader;
+use self::rewind::RewindBuffer;
 
 pub use controls::ControlBind;
 pub use controls::ControlEvent;
@@ -29,6 +55,10 @@ pub use controls::VirtualKeyCode;
 pub use cgmath;
 pub use image;
 pub use glium::glutin::dpi::LogicalSize;
+pub use glium::glutin::dpi::PhysicalSize;
+pub use glium::glutin::event::MouseButton;
+pub use glium::glutin::event::MouseScrollDelta;
+pub use glium::glutin::error::ExternalError;
 pub use glium::texture::Texture2d;
 pub use self::graphics::color::Color;
 pub use self::graphics::TexturelessSceneObject3d;
@@ -36,6 +66,7 @@ pub use self::graphics::TexturedSceneObject3d;
 pub use self::graphics::TexturelessSceneObject2d;
 pub use self::graphics::TexturedSceneObject2d;
 pub use self::graphics::create::SceneObjectCreator;
+pub use self::graphics::create::SceneObjectCreationError;
 pub use self::graphics::render::SceneSettings;
 pub use self::graphics::render::SceneRenderer;
 pub use self::graphics::render::SceneObjectRenderer;
@@ -43,14 +74,106 @@ pub use self::graphics::render::Projection;
 pub use self::graphics::render::Camera;
 pub use self::graphics::render::TEXT_NUM_LINES;
 pub use self::graphics::render::OverlayAlignment;
+pub use self::graphics::pick::Ray;
+pub use self::headless::HeadlessRunner;
+pub use self::clock::Clock;
+pub use self::clock::RealClock;
+pub use self::clock::MockClock;
+pub use self::record::Recording;
+pub use self::save::Savable;
+pub use self::error::EngineError;
 
 #[derive(Debug)]
-pub enum Event<FireTarget, SwitchTarget, ValueTarget> {
+pub enum Event<FireTarget, SwitchTarget, ValueTarget, GameEvent> {
     ControlEvent(ControlEvent<FireTarget, SwitchTarget, ValueTarget>),
+    GameEvent(GameEvent),
     WindowFocusChanged(bool),
     CloseRequested,
+    GameStarted,
+    GamePaused,
+    GameResumed,
     GameUpdated,
+    GameUpdatesSkipped(u64),
+    GameEnded,
+    GameClosed,
     CursorMoved,
+    RenderFailed(EngineError),
+    WindowStateChanged(WindowState),
+    WindowResized(LogicalSize),
+    ScaleFactorChanged(f64),
+    TextInput(char),
+    TextEditKey(TextEditKey),
+    // Positions are in overlay coordinates with centered alignment
+    MouseButton { button: MouseButton, pressed: bool, position: Vector2<f64> },
+    MouseWheel { delta: MouseScrollDelta, position: Vector2<f64> },
+    // Raw movement of the mouse, only reported while the cursor is captured or hidden
+    MouseMotion { delta: (f64, f64) },
+    // The input that was pressed or moved while the controls were capturing
+    InputCaptured(CapturedInput),
+    // The replay stopped at an input it couldn't reproduce, the game continues with live input
+    ReplayFailed(io::Error),
+}
+
+// Keys for editing text that are reported while text input is enabled
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TextEditKey {
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    Enter,
+    Escape,
+}
+
+impl TextEditKey {
+    fn from_key_code(key_code: VirtualKeyCode) -> Option<Self> {
+        match key_code {
+            VirtualKeyCode::Back => Some(TextEditKey::Backspace),
+            VirtualKeyCode::Delete => Some(TextEditKey::Delete),
+            VirtualKeyCode::Left => Some(TextEditKey::Left),
+            VirtualKeyCode::Right => Some(TextEditKey::Right),
+            VirtualKeyCode::Up => Some(TextEditKey::Up),
+            VirtualKeyCode::Down => Some(TextEditKey::Down),
+            VirtualKeyCode::Home => Some(TextEditKey::Home),
+            VirtualKeyCode::End => Some(TextEditKey::End),
+            VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => Some(TextEditKey::Enter),
+            VirtualKeyCode::Escape => Some(TextEditKey::Escape),
+            _ => None,
+        }
+    }
+}
+
+// Axes have to move at least this far to be captured, the axes of mice are never captured
+const AXIS_CAPTURE_THRESHOLD: f64 = 0.5;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CapturedInput {
+    Key(VirtualKeyCode),
+    Button(u32),
+    Axis { axis: u32, value: f64 },
+}
+
+impl CapturedInput {
+    fn from_device_event(event: &DeviceEvent) -> Option<Self> {
+        match *event {
+            DeviceEvent::Key(KeyboardInput {
+                state: ElementState::Pressed,
+                virtual_keycode: Some(key_code),
+                ..
+            }) => Some(CapturedInput::Key(key_code)),
+            DeviceEvent::Button { button, state: ElementState::Pressed } => {
+                Some(CapturedInput::Button(button))
+            },
+            DeviceEvent::Motion { axis, value } if value.abs() >= AXIS_CAPTURE_THRESHOLD => {
+                Some(CapturedInput::Axis { axis, value })
+            },
+            _ => None,
+        }
+    }
 }
 
 pub trait Application {
@@ -68,7 +191,7 @@ pub trait Application {
 
     fn handle_event(
         &mut self,
-        event: Event<Self::FireTarget, Self::SwitchTarget, Self::ValueTarget>,
+        event: Event<Self::FireTarget, Self::SwitchTarget, Self::ValueTarget, <Self::G as Game>::Event>,
         controller: ApplicationController<Self>,
     );
 
@@ -87,87 +210,394 @@ pub enum GameStatus {
 }
 
 pub trait Game {
-    fn update(&mut self) -> GameStatus;
+    type Command;
+    type Event;
+
+    fn update(
+        &mut self,
+        commands: &[Self::Command],
+        events: &mut Vec<Self::Event>,
+    ) -> GameStatus;
+}
+
+pub trait Interpolate {
+    fn interpolate(&self, other: &Self, t: f32) -> Self;
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct CatchUpPolicy {
+    pub max_updates_per_iteration: u32,
+    pub max_lag: Option<Duration>, // Updates that are further behind than this are skipped
+}
+
+impl Default for CatchUpPolicy {
+    fn default() -> Self {
+        CatchUpPolicy {
+            max_updates_per_iteration: 5,
+            max_lag: Some(Duration::from_millis(250)),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct GameSettings {
+    pub update_rate: u32,
+    pub time_scale: f64,
+    pub catch_up_policy: CatchUpPolicy,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        GameSettings {
+            update_rate: 50,
+            time_scale: 1.0,
+            catch_up_policy: Default::default(),
+        }
+    }
 }
 
 pub struct ApplicationController<'a, A: ?Sized + Application> {
     pub game_controller: GameController<'a, A::G>,
     pub cursor_controller: CursorController<'a>,
+    pub window_controller: WindowController<'a>,
+    pub controls_controller: ControlsController<'a, A>,
     closing: &'a mut bool,
+    text_input: &'a mut bool,
 }
 
 impl<'a, A: Application> ApplicationController<'a, A> {
     pub fn close(self) {
         *self.closing = true;
     }
+
+    pub fn text_input(&self) -> bool {
+        *self.text_input
+    }
+
+    // While text input is enabled, typed characters and editing keys are reported
+    // as text input events and no control events are emitted
+    pub fn set_text_input(&mut self, text_input: bool) {
+        *self.text_input = text_input;
+    }
 }
 
 pub struct GameInfo<'a, G: Game> {
     pub game: &'a G,
+    pub previous_game: Option<&'a G>, // Only kept if requested by the application
+    pub update_progress: f32, // Fraction of the time between the last and the next update that has passed
     pub paused: bool,
     pub ended: bool,
+    pub turbo: bool, // Updates as fast as possible instead of at the update rate
+    pub tick: u64, // Number of updates since the game started
+    pub game_time: Duration, // Time the updates so far have covered, without pauses
+    pub update_rate: u32,
+}
+
+impl<'a, G: Game + Interpolate> GameInfo<'a, G> {
+    pub fn interpolated(&self) -> Option<G> {
+        self.previous_game.map(|pg| pg.interpolate(self.game, self.update_progress))
+    }
 }
 
 pub struct RunningGameController<'a, G: Game> {
     game_data: &'a mut Option<GameData<G>>,
+    notifications: &'a mut VecDeque<GameNotification<G::Event>>,
+    now: Instant,
 }
 
 impl<'a, G: Game> RunningGameController<'a, G> {
+    pub fn update_rate(&self) -> u32 {
+        self.game_data.as_ref().unwrap().update_rate
+    }
+
+    pub fn set_update_rate(&mut self, update_rate: u32) {
+        self.game_data.as_mut().unwrap().set_update_rate(update_rate);
+    }
+
+    pub fn time_scale(&self) -> f64 {
+        self.game_data.as_ref().unwrap().time_scale
+    }
+
+    pub fn set_time_scale(&mut self, time_scale: f64) {
+        self.game_data.as_mut().unwrap().set_time_scale(time_scale);
+    }
+
+    pub fn turbo(&self) -> bool {
+        self.game_data.as_ref().unwrap().turbo
+    }
+
+    // In turbo mode the game updates until the next frame is due instead of at the update rate
+    pub fn set_turbo(&mut self, turbo: bool) {
+        self.game_data.as_mut().unwrap().set_turbo(turbo, self.now);
+    }
+
+    pub fn keep_previous_game(&mut self) where G: Clone {
+        self.game_data.as_mut().unwrap().clone_game = Some(G::clone);
+    }
+
+    // Keeps a snapshot every interval ticks, up to capacity snapshots,
+    // so the game can be rewound while it's paused
+    pub fn keep_rewind_buffer(&mut self, interval: u64, capacity: usize)
+    where G: Clone, G::Command: Clone {
+        self.game_data.as_mut().unwrap().rewind_buffer = Some(RewindBuffer::new(interval, capacity));
+    }
+
+    // The command is passed to the game with the next update
+    pub fn send(&mut self, command: G::Command) {
+        self.game_data.as_mut().unwrap().commands.push(command);
+    }
+
+    pub fn start_recording(&mut self) where G::Command: ToString {
+        let gd = self.game_data.as_mut().unwrap();
+        gd.recorder = Some(Recorder::new(gd.tick));
+    }
+
+    pub fn take_recording(&mut self) -> Option<Recording> {
+        self.game_data.as_mut().unwrap().take_recording()
+    }
+
+    pub fn save<W: Write>(&self, mut writer: W) -> io::Result<()> where G: Savable {
+        self.game_data.as_ref().unwrap().save(&mut writer)
+    }
+
+    pub fn load<R: Read>(&mut self, mut reader: R) -> io::Result<()> where G: Savable {
+        self.game_data.as_mut().unwrap().load(&mut reader, self.now)
+    }
+
     pub fn pause(self) -> PausedGameController<'a, G> {
-        self.game_data.as_mut().unwrap().pause_start = Some(Instant::now());
-        PausedGameController { game_data: self.game_data }
+        self.game_data.as_mut().unwrap().pause_start = Some(self.now);
+        self.notifications.push_back(GameNotification::Paused);
+        PausedGameController {
+            game_data: self.game_data,
+            notifications: self.notifications,
+            now: self.now,
+        }
     }
 
     pub fn close(self) -> ClosedGameController<'a, G> {
         *self.game_data = None;
-        ClosedGameController { game_data: self.game_data }
+        self.notifications.push_back(GameNotification::Closed);
+        ClosedGameController {
+            game_data: self.game_data,
+            notifications: self.notifications,
+            now: self.now,
+        }
     }
 }
 
 pub struct PausedGameController<'a, G: Game> {
     game_data: &'a mut Option<GameData<G>>,
+    notifications: &'a mut VecDeque<GameNotification<G::Event>>,
+    now: Instant,
 }
 
 impl<'a, G: Game> PausedGameController<'a, G> {
+    pub fn update_rate(&self) -> u32 {
+        self.game_data.as_ref().unwrap().update_rate
+    }
+
+    pub fn set_update_rate(&mut self, update_rate: u32) {
+        self.game_data.as_mut().unwrap().set_update_rate(update_rate);
+    }
+
+    pub fn time_scale(&self) -> f64 {
+        self.game_data.as_ref().unwrap().time_scale
+    }
+
+    pub fn set_time_scale(&mut self, time_scale: f64) {
+        self.game_data.as_mut().unwrap().set_time_scale(time_scale);
+    }
+
+    pub fn take_recording(&mut self) -> Option<Recording> {
+        self.game_data.as_mut().unwrap().take_recording()
+    }
+
+    pub fn save<W: Write>(&self, mut writer: W) -> io::Result<()> where G: Savable {
+        self.game_data.as_ref().unwrap().save(&mut writer)
+    }
+
+    // The loaded game stays paused
+    pub fn load<R: Read>(&mut self, mut reader: R) -> io::Result<()> where G: Savable {
+        self.game_data.as_mut().unwrap().load(&mut reader, self.now)
+    }
+
+    // Returns false if the tick isn't in the rewind buffer.
+    // A running recording ends at the tick the game was rewound from and can still be taken.
+    pub fn rewind_to(&mut self, tick: u64) -> bool {
+        let gd = self.game_data.as_mut().unwrap();
+        if !gd.rewind_to(tick) {
+            return false;
+        }
+        if gd.ended {
+            self.notifications.push_back(GameNotification::Ended);
+        }
+        true
+    }
+
+    // Updates the game once, with the buffered commands if it was rewound
+    pub fn step_forward(&mut self) {
+        self.step(1);
+    }
+
+    // Updates the game n times without resuming it, stops early if the game ends.
+    // The steps don't count towards the update schedule, so resuming doesn't jump.
+    pub fn step(&mut self, n: u64) {
+        let gd = self.game_data.as_mut().unwrap();
+        for _ in 0..n {
+            if gd.ended {
+                break;
+            }
+            gd.step_forward();
+            let game_events = mem::take(&mut gd.events);
+            self.notifications.push_back(GameNotification::Updated(game_events));
+            if gd.ended {
+                self.notifications.push_back(GameNotification::Ended);
+            }
+        }
+    }
+
     pub fn resume(self) -> RunningGameController<'a, G> {
         let gd = self.game_data.as_mut().unwrap();
-        gd.update_ref_time += Instant::now() - gd.pause_start.unwrap();
+        // the game takes its own course from here
+        let tick = gd.tick;
+        if let Some(ref mut rewind_buffer) = gd.rewind_buffer {
+            rewind_buffer.truncate(tick);
+        }
+        gd.update_ref_time += self.now - gd.pause_start.unwrap();
         gd.pause_start = None;
-        RunningGameController { game_data: self.game_data }
+        self.notifications.push_back(GameNotification::Resumed);
+        RunningGameController {
+            game_data: self.game_data,
+            notifications: self.notifications,
+            now: self.now,
+        }
     }
 
     pub fn close(self) -> ClosedGameController<'a, G> {
         *self.game_data = None;
-        ClosedGameController { game_data: self.game_data }
+        self.notifications.push_back(GameNotification::Closed);
+        ClosedGameController {
+            game_data: self.game_data,
+            notifications: self.notifications,
+            now: self.now,
+        }
     }
 }
 
 pub struct EndedGameController<'a, G: Game> {
     game_data: &'a mut Option<GameData<G>>,
+    notifications: &'a mut VecDeque<GameNotification<G::Event>>,
+    now: Instant,
 }
 
 impl<'a, G: Game> EndedGameController<'a, G> {
+    pub fn take_recording(&mut self) -> Option<Recording> {
+        self.game_data.as_mut().unwrap().take_recording()
+    }
+
+    pub fn save<W: Write>(&self, mut writer: W) -> io::Result<()> where G: Savable {
+        self.game_data.as_ref().unwrap().save(&mut writer)
+    }
+
+    // The loaded game continues running, the ended game is kept if loading fails
+    pub fn load<R: Read>(self, mut reader: R) -> io::Result<RunningGameController<'a, G>>
+    where G: Savable {
+        self.game_data.as_mut().unwrap().load(&mut reader, self.now)?;
+        self.notifications.push_back(GameNotification::Started);
+        Ok(RunningGameController {
+            game_data: self.game_data,
+            notifications: self.notifications,
+            now: self.now,
+        })
+    }
+
     pub fn close(self) -> ClosedGameController<'a, G> {
         *self.game_data = None;
-        ClosedGameController { game_data: self.game_data }
+        self.notifications.push_back(GameNotification::Closed);
+        ClosedGameController {
+            game_data: self.game_data,
+            notifications: self.notifications,
+            now: self.now,
+        }
     }
 }
 
 pub struct ClosedGameController<'a, G: Game> {
     game_data: &'a mut Option<GameData<G>>,
+    notifications: &'a mut VecDeque<GameNotification<G::Event>>,
+    now: Instant,
 }
 
 impl<'a, G: Game> ClosedGameController<'a, G> {
     pub fn start_new(self, game: G) -> RunningGameController<'a, G> {
+        self.start_new_with_settings(game, Default::default())
+    }
+
+    pub fn start_new_with_settings(
+        self,
+        game: G,
+        settings: GameSettings,
+    ) -> RunningGameController<'a, G> {
+        assert_valid_update_rate(settings.update_rate);
+        assert_valid_time_scale(settings.time_scale);
+        assert!(
+            settings.catch_up_policy.max_updates_per_iteration > 0,
+            "max_updates_per_iteration must be greater than 0"
+        );
         *self.game_data = Some(GameData {
             game,
             pause_start: None,
             ended: false,
-            update_ref_time: Instant::now(),
+            update_ref_time: self.now,
             num_updates: 0,
-            update_rate: 50,
+            turbo: false,
+            update_rate: settings.update_rate,
+            time_scale: settings.time_scale,
+            catch_up_policy: settings.catch_up_policy,
+            previous_game: None,
+            clone_game: None,
+            commands: Vec::new(),
+            events: Vec::new(),
+            tick: 0,
+            game_time: Duration::from_secs(0),
+            recorder: None,
+            finished_recording: None,
+            replay: None,
+            rewind_buffer: None,
         });
-        RunningGameController { game_data: self.game_data }
+        self.notifications.push_back(GameNotification::Started);
+        RunningGameController {
+            game_data: self.game_data,
+            notifications: self.notifications,
+            now: self.now,
+        }
+    }
+
+    // Starts a game whose inputs come from the recording instead of the application
+    pub fn start_replay(
+        self,
+        game: G,
+        settings: GameSettings,
+        recording: &Recording,
+    ) -> io::Result<RunningGameController<'a, G>> where G::Command: FromStr {
+        let replay = Replay::new(recording, 0)?;
+        let mut c = self.start_new_with_settings(game, settings);
+        c.game_data.as_mut().unwrap().replay = Some(replay);
+        Ok(c)
+    }
+
+    // Starts a saved game with the update rate it was saved with
+    pub fn load<R: Read>(self, mut reader: R) -> io::Result<RunningGameController<'a, G>>
+    where G: Savable {
+        let header = SaveHeader::read(&mut reader)?;
+        let game = G::load(&mut reader)?;
+        let settings = GameSettings { update_rate: header.update_rate, ..Default::default() };
+        let mut c = self.start_new_with_settings(game, settings);
+        let gd = c.game_data.as_mut().unwrap();
+        gd.tick = header.tick;
+        gd.game_time = header.game_time;
+        gd.num_updates = 1;
+        Ok(c)
     }
 }
 
@@ -178,261 +608,1310 @@ pub enum GameController<'a, G: Game> {
     Closed(ClosedGameController<'a, G>),
 }
 
-pub struct FreeCursorController<'a> {
+impl<'a, G: Game> GameController<'a, G> {
+    fn new(
+        game_data: &'a mut Option<GameData<G>>,
+        notifications: &'a mut VecDeque<GameNotification<G::Event>>,
+        now: Instant,
+    ) -> Self {
+        let state = game_data.as_ref().map(|gd| (gd.ended, gd.paused()));
+        match state {
+            Some((true, _)) => GameController::Ended(
+                EndedGameController { game_data, notifications, now }
+            ),
+            Some((false, true)) => GameController::Paused(
+                PausedGameController { game_data, notifications, now }
+            ),
+            Some((false, false)) => GameController::Running(
+                RunningGameController { game_data, notifications, now }
+            ),
+            None => GameController::Closed(
+                ClosedGameController { game_data, notifications, now }
+            ),
+        }
+    }
+}
+
+// Game state changes that are reported to the application after the current event
+enum GameNotification<GE> {
+    Started,
+    Paused,
+    Resumed,
+    Updated(Vec<GE>),
+    Ended,
+    Closed,
+}
+
+impl<GE> GameNotification<GE> {
+    fn events<F, S, V>(self) -> Vec<Event<F, S, V, GE>> {
+        match self {
+            GameNotification::Started => vec![Event::GameStarted],
+            GameNotification::Paused => vec![Event::GamePaused],
+            GameNotification::Resumed => vec![Event::GameResumed],
+            GameNotification::Updated(game_events) => game_events.into_iter()
+                .map(Event::GameEvent)
+                .chain(Some(Event::GameUpdated))
+                .collect(),
+            GameNotification::Ended => vec![Event::GameEnded],
+            GameNotification::Closed => vec![Event::GameClosed],
+        }
+    }
+}
+
+// Drawn by the engine instead of the system cursor,
+// the hotspot is the point of the object at the cursor position
+#[derive(Clone)]
+pub struct CursorSprite {
+    pub object: Rc<TexturedSceneObject2d<Rc<Texture2d>>>,
+    pub hotspot: Vector2<f32>,
+}
+
+// Position, sprite and coordinate conversions of a shown cursor,
+// the free, captured and confined cursor controllers dereference to it
+pub struct VisibleCursorController<'a> {
     cursor_data: &'a mut CursorData,
-    window: &'a Window,
+    window: Option<&'a Window>,
 }
 
-impl<'a> FreeCursorController<'a> {
-    pub fn position(&self) -> (f64, f64) { // TODO should this be relative to window size?
+impl<'a> VisibleCursorController<'a> {
+    // In logical window coordinates
+    pub fn position(&self) -> (f64, f64) {
         (self.cursor_data.pos.x, self.cursor_data.pos.y)
     }
 
-    pub fn capture(self) -> CapturedCursorController<'a> {
-        self.window.set_cursor_grab(true).ok(); // TODO what to do on error?
-        self.cursor_data.mode = CursorMode::Captured;
-        CapturedCursorController {
-            cursor_data: self.cursor_data,
-            window: self.window,
+    pub fn overlay_position(&self, alignment: OverlayAlignment) -> Vector2<f64> {
+        self.cursor_data.overlay_position(alignment)
+    }
+
+    pub fn ndc_position(&self) -> Vector2<f64> {
+        self.cursor_data.ndc_position()
+    }
+
+    pub fn transform(&self) -> ScreenTransform {
+        self.cursor_data.transform()
+    }
+
+    pub fn set_sprite(&mut self, sprite: Option<CursorSprite>) {
+        self.cursor_data.set_sprite(self.window, sprite);
+    }
+
+    // In logical window coordinates, other positions can be converted with the transform first
+    pub fn warp_to(&mut self, position: (f64, f64)) -> Result<(), ExternalError> {
+        self.cursor_data.warp_to(self.window, LogicalPosition::new(position.0, position.1))
+    }
+
+    fn set_mode(&mut self, mode: CursorMode) -> Result<(), ExternalError> {
+        self.cursor_data.set_mode(self.window, mode)
+    }
+}
+
+// Keeps the cursor inside the rectangle between min and max, in logical window coordinates
+fn confined_mode(min: (f64, f64), max: (f64, f64)) -> CursorMode {
+    CursorMode::Confined {
+        min: LogicalPosition::new(min.0.min(max.0), min.1.min(max.1)),
+        max: LogicalPosition::new(min.0.max(max.0), min.1.max(max.1)),
+    }
+}
+
+// Cursor transitions fail if the window system refuses to grab the cursor,
+// the controller of the unchanged mode is returned with the error then
+pub struct FreeCursorController<'a> {
+    cursor: VisibleCursorController<'a>,
+}
+
+impl<'a> Deref for FreeCursorController<'a> {
+    type Target = VisibleCursorController<'a>;
+
+    fn deref(&self) -> &VisibleCursorController<'a> {
+        &self.cursor
+    }
+}
+
+impl<'a> DerefMut for FreeCursorController<'a> {
+    fn deref_mut(&mut self) -> &mut VisibleCursorController<'a> {
+        &mut self.cursor
+    }
+}
+
+impl<'a> FreeCursorController<'a> {
+    pub fn capture(mut self) -> Result<CapturedCursorController<'a>, (Self, ExternalError)> {
+        match self.cursor.set_mode(CursorMode::Captured) {
+            Ok(()) => Ok(CapturedCursorController { cursor: self.cursor }),
+            Err(error) => Err((self, error)),
+        }
+    }
+
+    pub fn hide(mut self) -> Result<HiddenCursorController<'a>, (Self, ExternalError)> {
+        match self.cursor.set_mode(CursorMode::Hidden) {
+            Ok(()) => Ok(HiddenCursorController {
+                cursor_data: self.cursor.cursor_data,
+                window: self.cursor.window,
+            }),
+            Err(error) => Err((self, error)),
         }
     }
 
-    pub fn hide(self) -> HiddenCursorController<'a> {
-        self.window.set_cursor_visible(false);
-        self.window.set_cursor_grab(true).ok(); // TODO what to do on error?
-        self.cursor_data.mode = CursorMode::Hidden;
-        HiddenCursorController {
-            cursor_data: self.cursor_data,
-            window: self.window,
+    pub fn confine(
+        mut self,
+        min: (f64, f64),
+        max: (f64, f64),
+    ) -> Result<ConfinedCursorController<'a>, (Self, ExternalError)> {
+        match self.cursor.set_mode(confined_mode(min, max)) {
+            Ok(()) => Ok(ConfinedCursorController { cursor: self.cursor }),
+            Err(error) => Err((self, error)),
         }
     }
 }
 
 pub struct CapturedCursorController<'a> {
-    cursor_data: &'a mut CursorData,
-    window: &'a Window,
+    cursor: VisibleCursorController<'a>,
+}
+
+impl<'a> Deref for CapturedCursorController<'a> {
+    type Target = VisibleCursorController<'a>;
+
+    fn deref(&self) -> &VisibleCursorController<'a> {
+        &self.cursor
+    }
+}
+
+impl<'a> DerefMut for CapturedCursorController<'a> {
+    fn deref_mut(&mut self) -> &mut VisibleCursorController<'a> {
+        &mut self.cursor
+    }
 }
 
 impl<'a> CapturedCursorController<'a> {
-    pub fn position(&self) -> (f64, f64) { // TODO should this be relative to window size?
-        (self.cursor_data.pos.x, self.cursor_data.pos.y)
+    pub fn free(mut self) -> Result<FreeCursorController<'a>, (Self, ExternalError)> {
+        match self.cursor.set_mode(CursorMode::Normal) {
+            Ok(()) => Ok(FreeCursorController { cursor: self.cursor }),
+            Err(error) => Err((self, error)),
+        }
     }
 
-    pub fn free(self) -> FreeCursorController<'a> {
-        self.window.set_cursor_visible(true);
-        self.window.set_cursor_grab(false).ok(); // TODO what to do on error?
-        self.cursor_data.mode = CursorMode::Normal;
-        FreeCursorController {
-            cursor_data: self.cursor_data,
-            window: self.window,
+    pub fn hide(mut self) -> Result<HiddenCursorController<'a>, (Self, ExternalError)> {
+        match self.cursor.set_mode(CursorMode::Hidden) {
+            Ok(()) => Ok(HiddenCursorController {
+                cursor_data: self.cursor.cursor_data,
+                window: self.cursor.window,
+            }),
+            Err(error) => Err((self, error)),
         }
     }
 
-    pub fn hide(self) -> HiddenCursorController<'a> {
-        self.window.set_cursor_visible(false);
-        self.window.set_cursor_grab(true).ok(); // TODO what to do on error?
-        self.cursor_data.mode = CursorMode::Hidden;
-        HiddenCursorController {
-            cursor_data: self.cursor_data,
-            window: self.window,
+    pub fn confine(
+        mut self,
+        min: (f64, f64),
+        max: (f64, f64),
+    ) -> Result<ConfinedCursorController<'a>, (Self, ExternalError)> {
+        match self.cursor.set_mode(confined_mode(min, max)) {
+            Ok(()) => Ok(ConfinedCursorController { cursor: self.cursor }),
+            Err(error) => Err((self, error)),
         }
     }
 }
 
 pub struct HiddenCursorController<'a> {
     cursor_data: &'a mut CursorData,
-    window: &'a Window,
+    window: Option<&'a Window>,
 }
 
 impl<'a> HiddenCursorController<'a> {
-    pub fn show(self) -> FreeCursorController<'a> {
-        self.window.set_cursor_visible(true);
-        self.window.set_cursor_grab(false).ok(); // TODO what to do on error?
-        self.window.set_cursor_position(self.cursor_data.pos).ok(); // TODO what to do on error?
-        self.cursor_data.mode = CursorMode::Normal;
-        FreeCursorController {
-            cursor_data: self.cursor_data,
-            window: self.window,
+    pub fn transform(&self) -> ScreenTransform {
+        self.cursor_data.transform()
+    }
+
+    // The sprite is drawn once the cursor is shown again
+    pub fn set_sprite(&mut self, sprite: Option<CursorSprite>) {
+        self.cursor_data.set_sprite(self.window, sprite);
+    }
+
+    pub fn show(self) -> Result<FreeCursorController<'a>, (Self, ExternalError)> {
+        match self.cursor_data.set_mode(self.window, CursorMode::Normal) {
+            Ok(()) => Ok(FreeCursorController {
+                cursor: VisibleCursorController {
+                    cursor_data: self.cursor_data,
+                    window: self.window,
+                },
+            }),
+            Err(error) => Err((self, error)),
         }
     }
 
-    pub fn show_captured(self) -> CapturedCursorController<'a> {
-        self.window.set_cursor_visible(true);
-        self.window.set_cursor_grab(true).ok(); // TODO what to do on error?
-        self.window.set_cursor_position(self.cursor_data.pos).ok(); // TODO what to do on error?
-        self.cursor_data.mode = CursorMode::Captured;
-        CapturedCursorController {
-            cursor_data: self.cursor_data,
-            window: self.window,
+    pub fn show_captured(self) -> Result<CapturedCursorController<'a>, (Self, ExternalError)> {
+        match self.cursor_data.set_mode(self.window, CursorMode::Captured) {
+            Ok(()) => Ok(CapturedCursorController {
+                cursor: VisibleCursorController {
+                    cursor_data: self.cursor_data,
+                    window: self.window,
+                },
+            }),
+            Err(error) => Err((self, error)),
         }
     }
 }
 
-pub enum CursorController<'a> {
-    Free(FreeCursorController<'a>),
-    Captured(CapturedCursorController<'a>),
-    Hidden(HiddenCursorController<'a>),
+pub struct ConfinedCursorController<'a> {
+    cursor: VisibleCursorController<'a>,
 }
 
-pub struct GraphicsInfo {
-    pub fps: f32,
+impl<'a> Deref for ConfinedCursorController<'a> {
+    type Target = VisibleCursorController<'a>;
+
+    fn deref(&self) -> &VisibleCursorController<'a> {
+        &self.cursor
+    }
 }
 
-struct GameData<G: Game> {
-    game: G,
-    pause_start: Option<Instant>,
-    ended: bool,
-    update_ref_time: Instant,
-    num_updates: u64,
-    update_rate: u32,
+impl<'a> DerefMut for ConfinedCursorController<'a> {
+    fn deref_mut(&mut self) -> &mut VisibleCursorController<'a> {
+        &mut self.cursor
+    }
 }
 
-impl<G: Game> GameData<G> {
-    fn maybe_update(&mut self) -> bool {
-        match self.next_update_time() {
-            Some(nut) if nut <= Instant::now() => {
-                if let GameStatus::Ended = self.game.update() {
-                    self.ended = true;
-                }
-                self.num_updates += 1;
-                true
-            },
-            _ => false,
+impl<'a> ConfinedCursorController<'a> {
+    pub fn bounds(&self) -> ((f64, f64), (f64, f64)) {
+        match self.cursor.cursor_data.mode {
+            CursorMode::Confined { min, max } => ((min.x, min.y), (max.x, max.y)),
+            _ => unreachable!(),
         }
     }
 
-    fn game_info(&self) -> GameInfo<G> {
-        GameInfo {
-            game: &self.game,
-            paused: self.paused(),
-            ended: self.ended,
+    // True once the window system refused to move the cursor back inside the bounds.
+    // The reported position and the sprite stay inside them, but the system cursor can leave
+    pub fn software_confined(&self) -> bool {
+        self.cursor.cursor_data.software_confined
+    }
+
+    pub fn free(mut self) -> Result<FreeCursorController<'a>, (Self, ExternalError)> {
+        match self.cursor.set_mode(CursorMode::Normal) {
+            Ok(()) => Ok(FreeCursorController { cursor: self.cursor }),
+            Err(error) => Err((self, error)),
         }
     }
 
-    fn paused(&self) -> bool {
-        self.pause_start.is_some()
+    pub fn capture(mut self) -> Result<CapturedCursorController<'a>, (Self, ExternalError)> {
+        match self.cursor.set_mode(CursorMode::Captured) {
+            Ok(()) => Ok(CapturedCursorController { cursor: self.cursor }),
+            Err(error) => Err((self, error)),
+        }
     }
 
-    fn next_update_time(&self) -> Option<Instant> {
-        if self.paused() || self.ended {
-            return None;
+    pub fn hide(mut self) -> Result<HiddenCursorController<'a>, (Self, ExternalError)> {
+        match self.cursor.set_mode(CursorMode::Hidden) {
+            Ok(()) => Ok(HiddenCursorController {
+                cursor_data: self.cursor.cursor_data,
+                window: self.cursor.window,
+            }),
+            Err(error) => Err((self, error)),
         }
-        Some(next_tick_time(self.update_ref_time, self.num_updates, self.update_rate))
     }
 }
 
-struct GraphicsData {
-    graphics: Graphics,
-    render_ref_time: Instant,
-    num_renders: u64,
-    render_rate: u32,
-    render_phase: Duration,
-    last_render: Instant,
-    fps: f32,
+pub enum CursorController<'a> {
+    Free(FreeCursorController<'a>),
+    Captured(CapturedCursorController<'a>),
+    Hidden(HiddenCursorController<'a>),
+    Confined(ConfinedCursorController<'a>),
 }
 
-impl GraphicsData {
-    fn maybe_render<A: Application>(
-        &mut self,
-        application: &A,
-        display: &Display,
-        game_info: Option<GameInfo<A::G>>,
-    ) -> bool {
-        let next_render_time = self.next_render_time();
-        let now = Instant::now();
-        if now >= next_render_time {
-            // TODO adapt render_phase and render_rate properly
-            let start = Instant::now();
-            self.graphics.render(application, display, game_info, self.graphics_info());
-            let render_duration = Instant::now() - start;
-            if render_duration > Duration::from_millis(10) {
-                self.render_phase += render_duration - Duration::from_millis(3);
-            }
-            self.fps = self.fps * 0.95 + 0.05 / (now - self.last_render).as_secs_f32();
-            self.last_render = now;
-            self.num_renders += 1;
-            return true;
+impl<'a> CursorController<'a> {
+    fn new(cursor_data: &'a mut CursorData, window: Option<&'a Window>) -> Self {
+        match cursor_data.mode {
+            CursorMode::Normal => CursorController::Free(FreeCursorController {
+                cursor: VisibleCursorController { cursor_data, window },
+            }),
+            CursorMode::Captured => CursorController::Captured(CapturedCursorController {
+                cursor: VisibleCursorController { cursor_data, window },
+            }),
+            CursorMode::Hidden => CursorController::Hidden(HiddenCursorController { cursor_data, window }),
+            CursorMode::Confined { .. } => CursorController::Confined(ConfinedCursorController {
+                cursor: VisibleCursorController { cursor_data, window },
+            }),
         }
-        return false;
-    }
-
-    fn next_render_time(&self) -> Instant {
-        next_tick_time(
-            self.render_ref_time + self.render_phase,
-            self.num_renders,
-            self.render_rate
-        )
     }
 
-    fn graphics_info(&self) -> GraphicsInfo {
-        GraphicsInfo {
-            fps: self.fps,
+    pub fn transform(&self) -> ScreenTransform {
+        match self {
+            CursorController::Free(cc) => cc.transform(),
+            CursorController::Captured(cc) => cc.transform(),
+            CursorController::Hidden(cc) => cc.transform(),
+            CursorController::Confined(cc) => cc.transform(),
         }
     }
 }
 
-#[derive(Eq, PartialEq)]
-enum CursorMode {
-    Normal,
-    Captured,
-    Hidden,
+// Converts positions between logical window coordinates, normalized device coordinates
+// and overlay coordinates, for the window size it was taken at
+#[derive(Copy, Clone, Debug)]
+pub struct ScreenTransform {
+    window_size: LogicalSize,
+    overlay_transform: OverlayTransform,
 }
 
-struct CursorData {
+impl ScreenTransform {
+    fn new(window_size: LogicalSize, optimal_window_size: LogicalSize) -> Self {
+        let screen_ratio = window_size.width.max(1.0) / window_size.height.max(1.0);
+        ScreenTransform {
+            window_size,
+            overlay_transform: OverlayTransform::new(screen_ratio, optimal_window_size),
+        }
+    }
+
+    pub fn window_size(&self) -> LogicalSize {
+        self.window_size
+    }
+
+    pub fn window_to_ndc(&self, position: (f64, f64)) -> Vector2<f64> {
+        window_to_ndc(LogicalPosition::new(position.0, position.1), self.window_size)
+    }
+
+    pub fn ndc_to_window(&self, position: Vector2<f64>) -> (f64, f64) {
+        let position = ndc_to_window(position, self.window_size);
+        (position.x, position.y)
+    }
+
+    pub fn ndc_to_overlay(&self, position: Vector2<f64>, alignment: OverlayAlignment) -> Vector2<f64> {
+        self.overlay_transform.ndc_to_overlay(position, alignment)
+    }
+
+    pub fn overlay_to_ndc(&self, position: Vector2<f64>, alignment: OverlayAlignment) -> Vector2<f64> {
+        self.overlay_transform.overlay_to_ndc(position, alignment)
+    }
+
+    pub fn window_to_overlay(&self, position: (f64, f64), alignment: OverlayAlignment) -> Vector2<f64> {
+        self.ndc_to_overlay(self.window_to_ndc(position), alignment)
+    }
+
+    pub fn overlay_to_window(&self, position: Vector2<f64>, alignment: OverlayAlignment) -> (f64, f64) {
+        self.ndc_to_window(self.overlay_to_ndc(position, alignment))
+    }
+}
+
+// The size follows the resize events of the window. Fullscreen and maximized are only what was last requested,
+// the window system can refuse them or the user can leave them without the state changing
+#[derive(Clone, Debug, PartialEq)]
+pub struct WindowState {
+    pub title: String,
+    pub size: LogicalSize,
+    pub fullscreen: bool,
+    pub maximized: bool,
+    pub uncapped_rendering: bool,
+}
+
+pub struct WindowController<'a> {
+    window_data: &'a mut WindowData,
+    window: Option<&'a Window>,
+}
+
+impl<'a> WindowController<'a> {
+    pub fn state(&self) -> &WindowState {
+        &self.window_data.state
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        if let Some(window) = self.window {
+            window.set_title(title);
+        }
+        self.window_data.state.title = title.to_string();
+        self.window_data.changed = true;
+    }
+
+    pub fn set_size(&mut self, size: LogicalSize) {
+        if let Some(window) = self.window {
+            window.set_inner_size(size);
+        }
+        self.window_data.state.size = size;
+        self.window_data.changed = true;
+    }
+
+    pub fn set_fullscreen(&mut self, fullscreen: bool) {
+        if let Some(window) = self.window {
+            let monitor = if fullscreen { Some(window.current_monitor()) } else { None };
+            window.set_fullscreen(monitor);
+        }
+        self.window_data.state.fullscreen = fullscreen;
+        self.window_data.changed = true;
+    }
+
+    pub fn set_maximized(&mut self, maximized: bool) {
+        if let Some(window) = self.window {
+            window.set_maximized(maximized);
+        }
+        self.window_data.state.maximized = maximized;
+        self.window_data.changed = true;
+    }
+
+    // Uncapped frames are rendered as often as the loop runs instead of at the render rate.
+    // This doesn't touch the swap interval of the context, which keeps its default
+    pub fn set_uncapped_rendering(&mut self, uncapped_rendering: bool) {
+        self.window_data.state.uncapped_rendering = uncapped_rendering;
+        self.window_data.changed = true;
+    }
+}
+
+pub struct ControlsController<'a, A: ?Sized + Application> {
+    controls_data: &'a mut ControlsData<A>,
+}
+
+impl<'a, A: ?Sized + Application> ControlsController<'a, A> {
+    pub fn binds(&self) -> &[ControlBind<A::FireTarget, A::SwitchTarget, A::ValueTarget>] {
+        &self.controls_data.binds
+    }
+
+    pub fn add_bind(&mut self, bind: ControlBind<A::FireTarget, A::SwitchTarget, A::ValueTarget>) {
+        self.controls_data.controls.add_bind(bind.clone());
+        self.controls_data.binds.push(bind);
+    }
+
+    // Panics if there is no bind at the index, like Vec::remove
+    pub fn remove_bind(
+        &mut self,
+        index: usize,
+    ) -> ControlBind<A::FireTarget, A::SwitchTarget, A::ValueTarget> {
+        let bind = self.controls_data.binds.remove(index);
+        self.controls_data.rebuild();
+        bind
+    }
+
+    pub fn replace_bind(
+        &mut self,
+        index: usize,
+        bind: ControlBind<A::FireTarget, A::SwitchTarget, A::ValueTarget>,
+    ) -> ControlBind<A::FireTarget, A::SwitchTarget, A::ValueTarget> {
+        let old_bind = mem::replace(&mut self.controls_data.binds[index], bind);
+        self.controls_data.rebuild();
+        old_bind
+    }
+
+    pub fn capturing(&self) -> bool {
+        self.controls_data.capturing
+    }
+
+    // The next key, button or axis that is pressed or moved is reported as an input captured event
+    // instead of being passed to the controls, then capturing stops
+    pub fn start_capture(&mut self) {
+        self.controls_data.capturing = true;
+    }
+
+    pub fn cancel_capture(&mut self) {
+        self.controls_data.capturing = false;
+    }
+}
+
+pub struct GraphicsInfo {
+    pub fps: f32,
+    pub window_size: LogicalSize,
+    pub framebuffer_size: PhysicalSize,
+    pub scale_factor: f64,
+}
+
+struct GameData<G: Game> {
+    game: G,
+    pause_start: Option<Instant>,
+    ended: bool,
+    update_ref_time: Instant,
+    num_updates: u64,
+    turbo: bool,
+    update_rate: u32,
+    time_scale: f64,
+    catch_up_policy: CatchUpPolicy,
+    previous_game: Option<G>,
+    clone_game: Option<fn(&G) -> G>,
+    commands: Vec<G::Command>,
+    events: Vec<G::Event>,
+    tick: u64,
+    game_time: Duration,
+    recorder: Option<Recorder<G>>,
+    finished_recording: Option<Recording>, // Ended by a rewind or load, until it's taken
+    replay: Option<Replay<G>>,
+    rewind_buffer: Option<RewindBuffer<G>>,
+}
+
+impl<G: Game> GameData<G> {
+    fn maybe_update(&mut self, now: Instant) -> bool {
+        match self.next_update_time() {
+            Some(nut) if nut <= now => {
+                self.update_game();
+                self.num_updates += 1;
+                true
+            },
+            _ => false,
+        }
+    }
+
+    fn update_game(&mut self) {
+        if let Some(ref mut replay) = self.replay {
+            replay.take_commands(self.tick, &mut self.commands);
+        }
+        if let Some(ref mut recorder) = self.recorder {
+            recorder.record_commands(self.tick, &self.commands);
+        }
+        if let Some(ref mut rewind_buffer) = self.rewind_buffer {
+            rewind_buffer.record(self.tick, self.game_time, &self.game, &self.commands);
+        }
+        if let Some(clone_game) = self.clone_game {
+            self.previous_game = Some(clone_game(&self.game));
+        }
+        if let GameStatus::Ended = self.game.update(&self.commands, &mut self.events) {
+            self.ended = true;
+        }
+        self.commands.clear();
+        self.tick += 1;
+        self.game_time += Duration::from_secs(1) / self.update_rate;
+        if self.replay.as_ref().map_or(false, |r| r.finished(self.tick)) {
+            self.replay = None;
+        }
+    }
+
+    // updates the game once, a rewound tick gets the commands that were buffered for it
+    // and a new tick the ones that were sent
+    fn step_forward(&mut self) {
+        if let Some(ref rewind_buffer) = self.rewind_buffer {
+            if rewind_buffer.recorded(self.tick) {
+                rewind_buffer.commands(self.tick, &mut self.commands);
+            }
+        }
+        self.update_game();
+        // a game that was stepped to its end is loaded running again
+        if self.ended {
+            self.pause_start = None;
+        }
+    }
+
+    // restores the game from the latest snapshot before the tick
+    // and replays the buffered commands up to it
+    fn rewind_to(&mut self, tick: u64) -> bool {
+        let snapshot = self.rewind_buffer.as_ref().and_then(|rb| rb.snapshot(tick));
+        let (snapshot_tick, game_time, game) = match snapshot {
+            Some(snapshot) => snapshot,
+            None => return false,
+        };
+        // the recorded inputs don't match the game anymore
+        self.finish_recording();
+        self.replay = None;
+        self.game = game;
+        self.tick = snapshot_tick;
+        self.game_time = game_time;
+        self.ended = false;
+        self.commands.clear();
+        while self.tick < tick && !self.ended {
+            self.step_forward();
+        }
+        self.previous_game = None;
+        self.events.clear();
+        true
+    }
+
+    fn update_due(&self, now: Instant) -> bool {
+        self.next_update_time().map_or(false, |nut| nut <= now)
+    }
+
+    // the control events that were recorded for the update that is due,
+    // or for the current tick while the game is paused, because steps advance it without updates being due
+    fn take_replayed_control_events(&mut self, now: Instant) -> Vec<String> {
+        if !self.paused() && !self.update_due(now) {
+            return Vec::new();
+        }
+        match self.replay {
+            Some(ref mut replay) => replay.take_control_events(self.tick),
+            None => Vec::new(),
+        }
+    }
+
+    // a recording that was finished before is taken before the running one
+    fn take_recording(&mut self) -> Option<Recording> {
+        let tick = self.tick;
+        let recorder = &mut self.recorder;
+        self.finished_recording.take().or_else(|| recorder.take().map(|r| r.finish(tick)))
+    }
+
+    // ends the running recording at the current tick and keeps it until it's taken
+    fn finish_recording(&mut self) {
+        let tick = self.tick;
+        if let Some(recorder) = self.recorder.take() {
+            self.finished_recording = Some(recorder.finish(tick));
+        }
+    }
+
+    fn save(&self, writer: &mut dyn Write) -> io::Result<()> where G: Savable {
+        let header = SaveHeader {
+            tick: self.tick,
+            game_time: self.game_time,
+            update_rate: self.update_rate,
+        };
+        header.write(writer)?;
+        self.game.save(writer)?;
+        writer.flush()
+    }
+
+    // replaces the game and continues with the saved tick and update rate,
+    // with the next update one update period from now
+    fn load(&mut self, reader: &mut dyn Read, now: Instant) -> io::Result<()> where G: Savable {
+        let header = SaveHeader::read(reader)?;
+        self.game = G::load(reader)?;
+        self.ended = false;
+        self.tick = header.tick;
+        self.game_time = header.game_time;
+        self.update_rate = header.update_rate;
+        self.update_ref_time = now;
+        self.num_updates = 1;
+        if self.paused() {
+            self.pause_start = Some(now);
+        }
+        self.previous_game = None;
+        self.commands.clear();
+        self.events.clear();
+        self.finish_recording();
+        self.replay = None;
+        if let Some(ref mut rewind_buffer) = self.rewind_buffer {
+            rewind_buffer.truncate(0);
+        }
+        Ok(())
+    }
+
+    fn game_info(&self, now: Instant) -> GameInfo<G> {
+        GameInfo {
+            game: &self.game,
+            previous_game: self.previous_game.as_ref(),
+            update_progress: self.update_progress(now),
+            paused: self.paused(),
+            ended: self.ended,
+            turbo: self.turbo,
+            tick: self.tick,
+            game_time: self.game_time,
+            update_rate: self.update_rate,
+        }
+    }
+
+    fn update_progress(&self, now: Instant) -> f32 {
+        if self.ended || self.turbo || self.num_updates == 0 {
+            return 1.0;
+        }
+        let now = self.pause_start.unwrap_or(now);
+        let last_update_time = self.update_time(self.num_updates - 1);
+        let next_update_time = self.update_time(self.num_updates);
+        if now <= last_update_time {
+            return 0.0;
+        }
+        let progress = (now - last_update_time).as_secs_f32()
+            / (next_update_time - last_update_time).as_secs_f32();
+        progress.min(1.0)
+    }
+
+    fn paused(&self) -> bool {
+        self.pause_start.is_some()
+    }
+
+    fn next_update_time(&self) -> Option<Instant> {
+        if self.paused() || self.ended {
+            return None;
+        }
+        if self.turbo {
+            // always due
+            return Some(self.update_ref_time);
+        }
+        Some(self.update_time(self.num_updates))
+    }
+
+    fn update_time(&self, num_updates: u64) -> Instant {
+        let game_time = Duration::from_secs(num_updates) / self.update_rate;
+        if self.time_scale == 1.0 {
+            self.update_ref_time + game_time
+        } else {
+            self.update_ref_time + game_time.div_f64(self.time_scale)
+        }
+    }
+
+    fn set_update_rate(&mut self, update_rate: u32) {
+        assert_valid_update_rate(update_rate);
+        self.rebase();
+        self.update_rate = update_rate;
+    }
+
+    fn set_time_scale(&mut self, time_scale: f64) {
+        assert_valid_time_scale(time_scale);
+        self.rebase();
+        self.time_scale = time_scale;
+    }
+
+    // the schedule restarts from now in both directions,
+    // so leaving turbo mode doesn't cause a burst of updates to catch up
+    fn set_turbo(&mut self, turbo: bool, now: Instant) {
+        if turbo == self.turbo {
+            return;
+        }
+        self.turbo = turbo;
+        self.update_ref_time = now;
+        self.num_updates = if turbo { 0 } else { 1 };
+    }
+
+    // drops the updates that are due if the game is too far behind
+    // and schedules the next update for now, returns the number of dropped updates
+    fn skip_lagging_updates(&mut self, now: Instant) -> u64 {
+        let max_lag = match self.catch_up_policy.max_lag {
+            Some(max_lag) if !self.turbo => max_lag,
+            _ => return 0,
+        };
+        match self.next_update_time() {
+            Some(nut) if nut + max_lag < now => (),
+            _ => return 0,
+        }
+        let elapsed = now - self.update_ref_time;
+        let game_time = if self.time_scale == 1.0 {
+            elapsed
+        } else {
+            elapsed.mul_f64(self.time_scale)
+        };
+        let num_due = (game_time.as_nanos() * self.update_rate as u128 / 1_000_000_000) as u64 + 1;
+
+        // one of the due updates still happens now
+        let num_skipped = num_due.saturating_sub(self.num_updates + 1);
+        self.update_ref_time = now;
+        self.num_updates = 0;
+        num_skipped
+    }
+
+    // moves the reference time to the last update,
+    // so the next update is scheduled relative to it with the new timing
+    fn rebase(&mut self) {
+        if self.num_updates > 0 {
+            self.update_ref_time = self.update_time(self.num_updates - 1);
+            self.num_updates = 1;
+        }
+    }
+}
+
+struct GraphicsData {
+    graphics: Graphics,
+    render_timing: RenderTiming,
+    window_size: LogicalSize,
+    scale_factor: f64,
+}
+
+impl GraphicsData {
+    fn maybe_render<A: Application, C: Clock>(
+        &mut self,
+        application: &A,
+        display: &Display,
+        clock: &C,
+        game_info: Option<GameInfo<A::G>>,
+        uncapped: bool,
+        cursor: Option<(&CursorSprite, Vector2<f64>)>,
+    ) -> Result<bool, EngineError> {
+        let now = clock.now();
+        if uncapped == self.render_timing.paced {
+            self.render_timing.set_paced(!uncapped, now);
+        }
+        if now >= self.render_timing.next_render_time() {
+            let graphics_info = self.graphics_info();
+            let result = self.graphics.render(application, display, game_info, graphics_info, cursor);
+            self.render_timing.render_finished(now, clock.now());
+            return result.map(|()| true);
+        }
+        return Ok(false);
+    }
+
+    fn graphics_info(&self) -> GraphicsInfo {
+        GraphicsInfo {
+            fps: self.render_timing.fps,
+            window_size: self.window_size,
+            framebuffer_size: self.window_size.to_physical(self.scale_factor),
+            scale_factor: self.scale_factor,
+        }
+    }
+}
+
+struct RenderTiming {
+    render_ref_time: Instant,
+    num_renders: u64,
+    render_rate: u32,
+    render_phase: Duration,
+    last_render: Instant,
+    fps: f32,
+    paced: bool,
+}
+
+impl RenderTiming {
+    fn new(now: Instant, render_rate: u32) -> Self {
+        RenderTiming {
+            render_ref_time: now,
+            num_renders: 0,
+            render_rate,
+            render_phase: Duration::from_secs(0),
+            last_render: now - Duration::from_secs(1) / render_rate,
+            fps: 0.0,
+            paced: true,
+        }
+    }
+
+    // renders at the render rate when paced, otherwise whenever possible
+    fn set_paced(&mut self, paced: bool, now: Instant) {
+        self.paced = paced;
+        self.render_ref_time = now;
+        self.num_renders = 0;
+        self.render_phase = Duration::from_secs(0);
+    }
+
+    fn render_finished(&mut self, start: Instant, end: Instant) {
+        // TODO adapt render_phase and render_rate properly
+        let render_duration = end - start;
+        if render_duration > Duration::from_millis(10) {
+            self.render_phase += render_duration - Duration::from_millis(3);
+        }
+        self.fps = self.fps * 0.95 + 0.05 / (start - self.last_render).as_secs_f32();
+        self.last_render = start;
+        self.num_renders += 1;
+    }
+
+    fn next_render_time(&self) -> Instant {
+        if !self.paced {
+            return self.last_render;
+        }
+        next_tick_time(
+            self.render_ref_time + self.render_phase,
+            self.num_renders,
+            self.render_rate
+        )
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum CursorMode {
+    Normal,
+    Captured,
+    Hidden,
+    Confined { min: LogicalPosition, max: LogicalPosition },
+}
+
+impl CursorMode {
+    fn grabbed(&self) -> bool {
+        *self != CursorMode::Normal
+    }
+
+    fn visible(&self) -> bool {
+        *self != CursorMode::Hidden
+    }
+}
+
+struct CursorData {
     pos: LogicalPosition,
     mode: CursorMode,
+    window_size: LogicalSize,
+    optimal_window_size: LogicalSize,
+    sprite: Option<CursorSprite>,
+    // the window system refused to move the cursor back into the confinement bounds
+    software_confined: bool,
 }
 
-struct Engine<A: Application> {
-    application: A,
-    display: Display,
+impl CursorData {
+    fn transform(&self) -> ScreenTransform {
+        ScreenTransform::new(self.window_size, self.optimal_window_size)
+    }
+
+    fn ndc_position(&self) -> Vector2<f64> {
+        window_to_ndc(self.pos, self.window_size)
+    }
+
+    fn overlay_position(&self, alignment: OverlayAlignment) -> Vector2<f64> {
+        self.transform().ndc_to_overlay(self.ndc_position(), alignment)
+    }
+
+    fn warp_to(&mut self, window: Option<&Window>, position: LogicalPosition) -> Result<(), ExternalError> {
+        if let Some(window) = window {
+            window.set_cursor_position(position)?;
+        }
+        self.pos = position;
+        Ok(())
+    }
+
+    // changes the window's cursor to the mode, the grab is undone if a later step fails
+    fn set_mode(&mut self, window: Option<&Window>, mode: CursorMode) -> Result<(), ExternalError> {
+        if let Some(window) = window {
+            let grab_changed = mode.grabbed() != self.mode.grabbed();
+            if grab_changed {
+                window.set_cursor_grab(mode.grabbed())?;
+            }
+            // a cursor that is shown again appears where it was hidden
+            if mode.visible() && !self.mode.visible() {
+                if let Err(error) = window.set_cursor_position(self.pos) {
+                    if grab_changed {
+                        window.set_cursor_grab(self.mode.grabbed()).ok();
+                    }
+                    return Err(error);
+                }
+            }
+            window.set_cursor_visible(mode.visible() && self.sprite.is_none());
+        }
+        self.mode = mode;
+        self.software_confined = false;
+        Ok(())
+    }
+
+    // the system cursor is hidden while a sprite replaces it
+    fn set_sprite(&mut self, window: Option<&Window>, sprite: Option<CursorSprite>) {
+        if let Some(window) = window {
+            window.set_cursor_visible(self.mode.visible() && sprite.is_none());
+        }
+        self.sprite = sprite;
+    }
+
+    // the sprite to draw and its position in overlay coordinates with centered alignment
+    fn visible_sprite(&self) -> Option<(&CursorSprite, Vector2<f64>)> {
+        if !self.mode.visible() {
+            return None;
+        }
+        let position = self.overlay_position(OverlayAlignment::Center);
+        self.sprite.as_ref().map(|sprite| (sprite, position))
+    }
+
+    // the position inside the bounds of a confined cursor
+    fn confined_position(&self, position: LogicalPosition) -> LogicalPosition {
+        match self.mode {
+            CursorMode::Confined { min, max } => LogicalPosition::new(
+                position.x.max(min.x).min(max.x),
+                position.y.max(min.y).min(max.y),
+            ),
+            _ => position,
+        }
+    }
+}
+
+struct WindowData {
+    state: WindowState,
+    changed: bool,
+}
+
+struct ControlsData<A: ?Sized + Application> {
     controls: Controls<A::FireTarget, A::SwitchTarget, A::ValueTarget>,
+    binds: Vec<ControlBind<A::FireTarget, A::SwitchTarget, A::ValueTarget>>,
+    released_events: VecDeque<ControlEvent<A::FireTarget, A::SwitchTarget, A::ValueTarget>>,
+    paused: bool,
+    capturing: bool,
+    pending_axis_capture: Option<(DeviceId, CapturedInput)>,
+}
+
+impl<A: ?Sized + Application> ControlsData<A> {
+    fn new(binds: Vec<ControlBind<A::FireTarget, A::SwitchTarget, A::ValueTarget>>) -> Self {
+        let mut controls = Controls::new();
+        binds.iter().for_each(|bind| controls.add_bind(bind.clone()));
+        ControlsData {
+            controls,
+            binds,
+            released_events: VecDeque::new(),
+            paused: false,
+            capturing: false,
+            pending_axis_capture: None,
+        }
+    }
+
+    // Binds can't be removed from controls, so new controls are made from the remaining binds.
+    // The old controls are paused first, so what is held on them is released
+    fn rebuild(&mut self) {
+        let mut controls = Controls::new();
+        self.binds.iter().for_each(|bind| controls.add_bind(bind.clone()));
+        let mut old_controls = mem::replace(&mut self.controls, controls);
+        if self.paused {
+            self.controls.pause();
+        } else {
+            old_controls.pause();
+        }
+        old_controls.get_events(&mut self.released_events);
+    }
+
+    fn set_paused(&mut self, paused: bool) {
+        if paused != self.paused {
+            if paused {
+                self.controls.pause()
+            } else {
+                self.controls.resume()
+            }
+            self.paused = paused;
+        }
+    }
+
+    // events of replaced controls come before the events of the current ones
+    fn get_events(
+        &mut self,
+        events: &mut VecDeque<ControlEvent<A::FireTarget, A::SwitchTarget, A::ValueTarget>>,
+    ) {
+        self.controls.get_events(events);
+        while let Some(event) = self.released_events.pop_back() {
+            events.push_front(event);
+        }
+    }
+
+    // a mouse reports its movement as axis motion before the mouse motion or wheel event,
+    // so a moved axis is only captured if its device didn't turn out to be a mouse by the end of the events
+    fn capture(&mut self, device_id: DeviceId, event: &DeviceEvent) -> Option<CapturedInput> {
+        if !self.capturing {
+            return None;
+        }
+        match *event {
+            DeviceEvent::MouseMotion { .. } | DeviceEvent::MouseWheel { .. } => {
+                if self.pending_axis_capture.map_or(false, |(id, _)| id == device_id) {
+                    self.pending_axis_capture = None;
+                }
+                None
+            },
+            _ => match CapturedInput::from_device_event(event) {
+                Some(input @ CapturedInput::Axis { .. }) => {
+                    if self.pending_axis_capture.is_none() {
+                        self.pending_axis_capture = Some((device_id, input));
+                    }
+                    None
+                },
+                Some(input) => {
+                    self.pending_axis_capture = None;
+                    self.capturing = false;
+                    Some(input)
+                },
+                None => None,
+            },
+        }
+    }
+
+    // called once the events of a loop iteration are handled
+    fn finish_axis_capture(&mut self) -> Option<CapturedInput> {
+        let (_, input) = self.pending_axis_capture.take()?;
+        if !self.capturing {
+            return None;
+        }
+        self.capturing = false;
+        Some(input)
+    }
+}
+
+struct ApplicationData<A: Application> {
+    application: A,
     game_data: Option<GameData<A::G>>,
-    graphics_data: GraphicsData,
+    game_notifications: VecDeque<GameNotification<<A::G as Game>::Event>>,
     cursor_data: CursorData,
+    window_data: WindowData,
+    controls_data: ControlsData<A>,
     closing: bool,
+    text_input: bool,
+    // set when a control event enables text input, so the character of its key press isn't typed
+    skip_character: bool,
 }
 
-impl<A: Application> Engine<A> {
+impl<A: Application> ApplicationData<A> {
+    fn new(
+        application: A,
+        binds: Vec<ControlBind<A::FireTarget, A::SwitchTarget, A::ValueTarget>>,
+    ) -> Self {
+        ApplicationData {
+            application,
+            game_data: None,
+            game_notifications: VecDeque::new(),
+            cursor_data: CursorData {
+                pos: LogicalPosition::new(0.0, 0.0),
+                mode: CursorMode::Normal,
+                window_size: A::optimal_window_size(),
+                optimal_window_size: A::optimal_window_size(),
+                sprite: None,
+                software_confined: false,
+            },
+            window_data: WindowData {
+                state: WindowState {
+                    title: A::title().to_string(),
+                    size: A::optimal_window_size(),
+                    fullscreen: false,
+                    maximized: false,
+                    uncapped_rendering: false,
+                },
+                changed: false,
+            },
+            controls_data: ControlsData::new(binds),
+            closing: false,
+            text_input: false,
+            skip_character: false,
+        }
+    }
+
     fn emit_event(
         &mut self,
-        event: Event<A::FireTarget, A::SwitchTarget, A::ValueTarget>,
+        event: Event<A::FireTarget, A::SwitchTarget, A::ValueTarget, <A::G as Game>::Event>,
+        now: Instant,
+        window: Option<&Window>,
     ) {
-        let game_controller = match self.game_data {
-            Some(ref gd) => {
-                if gd.ended {
-                    GameController::Ended(EndedGameController { game_data: &mut self.game_data })
-                } else if gd.paused() {
-                    GameController::Paused(PausedGameController { game_data: &mut self.game_data })
-                } else {
-                    GameController::Running(RunningGameController { game_data: &mut self.game_data })
+        if let Event::ControlEvent(_) = event {
+            // live input would take the replayed game off its recorded course
+            if self.game_data.as_ref().map_or(false, |gd| gd.replay.is_some()) {
+                return;
+            }
+            let text_input = self.text_input;
+            self.emit_unfiltered_event(event, now, window);
+            if self.text_input && !text_input {
+                self.skip_character = true;
+            }
+        } else {
+            self.emit_unfiltered_event(event, now, window);
+        }
+    }
+
+    // typed characters are reported while text input is enabled, except the one of the key press
+    // that enabled it, which comes after its control event in the same batch of events
+    fn receive_character(&mut self, c: char, now: Instant, window: Option<&Window>) {
+        if !self.text_input || c.is_control() {
+            return;
+        }
+        if self.skip_character {
+            self.skip_character = false;
+            return;
+        }
+        self.emit_event(Event::TextInput(c), now, window);
+    }
+
+    fn start_event_batch(&mut self) {
+        self.skip_character = false;
+    }
+
+    fn emit_unfiltered_event(
+        &mut self,
+        event: Event<A::FireTarget, A::SwitchTarget, A::ValueTarget, <A::G as Game>::Event>,
+        now: Instant,
+        window: Option<&Window>,
+    ) {
+        if let Event::ControlEvent(ref control_event) = event {
+            if let Some(ref mut gd) = self.game_data {
+                if let Some(ref mut recorder) = gd.recorder {
+                    recorder.record_control_event(gd.tick, control_event_to_string(control_event));
                 }
-            },
-            None => GameController::Closed(ClosedGameController { game_data: &mut self.game_data })
-        };
-        let gl_window = self.display.gl_window();
-        let cursor_controller = match self.cursor_data.mode {
-            CursorMode::Normal => CursorController::Free(FreeCursorController {
-                cursor_data: &mut self.cursor_data,
-                window: gl_window.window(),
-            }),
-            CursorMode::Captured => CursorController::Captured(CapturedCursorController {
-                cursor_data: &mut self.cursor_data,
-                window: gl_window.window(),
-            }),
-            CursorMode::Hidden => CursorController::Hidden(HiddenCursorController {
-                cursor_data: &mut self.cursor_data,
-                window: gl_window.window(),
-            }),
-        };
+            }
+        }
+        self.dispatch_event(event, now, window);
+        self.dispatch_game_notifications(now, window);
+        self.dispatch_window_notifications(now, window);
+    }
+
+    // repeats until no events are left, the events of a paused game may step it to the next tick
+    fn emit_replayed_control_events(&mut self, now: Instant, window: Option<&Window>) {
+        loop {
+            let control_events = match self.game_data {
+                Some(ref mut gd) => gd.take_replayed_control_events(now),
+                None => return,
+            };
+            if control_events.is_empty() {
+                return;
+            }
+            for control_event in control_events {
+                match parse_control_event(&control_event) {
+                    Some(ce) => self.emit_unfiltered_event(Event::ControlEvent(ce), now, window),
+                    None => {
+                        // skipping the event would let the game take a different course
+                        if let Some(ref mut gd) = self.game_data {
+                            gd.replay = None;
+                        }
+                        let error = io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("invalid control event in recording: {}", control_event),
+                        );
+                        self.emit_event(Event::ReplayFailed(error), now, window);
+                        return;
+                    },
+                }
+            }
+        }
+    }
+
+    fn dispatch_game_notifications(&mut self, now: Instant, window: Option<&Window>) {
+        while let Some(notification) = self.game_notifications.pop_front() {
+            for event in notification.events() {
+                self.dispatch_event(event, now, window);
+            }
+        }
+    }
+
+    fn dispatch_window_notifications(&mut self, now: Instant, window: Option<&Window>) {
+        while self.window_data.changed {
+            self.window_data.changed = false;
+            let state = self.window_data.state.clone();
+            self.dispatch_event(Event::WindowStateChanged(state), now, window);
+        }
+    }
+
+    fn dispatch_event(
+        &mut self,
+        event: Event<A::FireTarget, A::SwitchTarget, A::ValueTarget, <A::G as Game>::Event>,
+        now: Instant,
+        window: Option<&Window>,
+    ) {
         let application_controller = ApplicationController {
-            game_controller,
-            cursor_controller,
+            game_controller: GameController::new(
+                &mut self.game_data,
+                &mut self.game_notifications,
+                now,
+            ),
+            cursor_controller: CursorController::new(&mut self.cursor_data, window),
+            window_controller: WindowController {
+                window_data: &mut self.window_data,
+                window,
+            },
+            controls_controller: ControlsController {
+                controls_data: &mut self.controls_data,
+            },
             closing: &mut self.closing,
+            text_input: &mut self.text_input,
         };
         self.application.handle_event(event, application_controller)
     }
 
+    fn maybe_update_game(&mut self, now: Instant, window: Option<&Window>) -> Option<Instant> {
+        let max_updates = match self.game_data {
+            Some(ref gd) => gd.catch_up_policy.max_updates_per_iteration,
+            None => return None,
+        };
+        for _ in 0..max_updates {
+            self.emit_replayed_control_events(now, window);
+            let (game_events, ended) = match self.game_data {
+                Some(ref mut gd) => {
+                    if !gd.maybe_update(now) {
+                        break;
+                    }
+                    (mem::take(&mut gd.events), gd.ended)
+                },
+                None => break,
+            };
+            for game_event in game_events {
+                self.emit_event(Event::GameEvent(game_event), now, window);
+            }
+            if ended {
+                self.game_notifications.push_back(GameNotification::Ended);
+            }
+            self.emit_event(Event::GameUpdated, now, window);
+        }
+        if let Some(ref mut gd) = self.game_data {
+            let num_skipped = gd.skip_lagging_updates(now);
+            if num_skipped > 0 {
+                self.emit_event(Event::GameUpdatesSkipped(num_skipped), now, window);
+            }
+        }
+        return self.game_data.as_ref().and_then(|gd| gd.next_update_time());
+    }
+
+    fn game_turbo(&self) -> bool {
+        self.game_data.as_ref().map_or(false, |gd| gd.turbo && gd.next_update_time().is_some())
+    }
+}
+
+struct Engine<A: Application, C: Clock> {
+    application_data: ApplicationData<A>,
+    clock: C,
+    display: Display,
+    focused: bool,
+    graphics_data: GraphicsData,
+}
+
+impl<A: Application, C: Clock> Engine<A, C> {
+    fn emit_event(
+        &mut self,
+        event: Event<A::FireTarget, A::SwitchTarget, A::ValueTarget, <A::G as Game>::Event>,
+    ) {
+        let gl_window = self.display.gl_window();
+        self.application_data.emit_event(event, self.clock.now(), Some(gl_window.window()));
+    }
+
     fn handle_event(&mut self, event: WinitEvent<()>) {
         let my_window_id = self.display.gl_window().window().id();
         match event {
@@ -441,29 +1920,68 @@ impl<A: Application> Engine<A> {
                     match we {
                         WindowEvent::Resized(size) => {
                             self.graphics_data.graphics.set_view_port_size(size);
+                            self.graphics_data.window_size = size;
+                            self.application_data.cursor_data.window_size = size;
+                            self.application_data.window_data.state.size = size;
+                            self.emit_event(Event::WindowResized(size));
+                        },
+                        WindowEvent::HiDpiFactorChanged(scale_factor) => {
+                            self.graphics_data.scale_factor = scale_factor;
+                            self.emit_event(Event::ScaleFactorChanged(scale_factor));
                         },
                         WindowEvent::CloseRequested => {
                             self.emit_event(Event::CloseRequested);
                         },
                         WindowEvent::Focused(focused) => {
-                            if focused {
-                                self.controls.resume()
-                            } else {
-                                self.controls.pause()
-                            }
+                            self.focused = focused;
+                            self.update_controls_paused();
                             self.emit_event(Event::WindowFocusChanged(focused));
                         },
+                        WindowEvent::ReceivedCharacter(c) => {
+                            let gl_window = self.display.gl_window();
+                            self.application_data.receive_character(c, self.clock.now(), Some(gl_window.window()));
+                        },
+                        WindowEvent::KeyboardInput { input, .. } => {
+                            if let KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(key_code),
+                                ..
+                            } = input {
+                                if self.application_data.text_input {
+                                    if let Some(edit_key) = TextEditKey::from_key_code(key_code) {
+                                        self.emit_event(Event::TextEditKey(edit_key));
+                                    }
+                                }
+                            }
+                        },
                         WindowEvent::Moved(_) => (),
                         WindowEvent::AxisMotion { .. } => (),
                         WindowEvent::CursorMoved { position, .. } => {
-                            if self.cursor_data.mode != CursorMode::Hidden {
-                                let old_pos = self.cursor_data.pos;
-                                self.cursor_data.pos = position;
-                                if self.cursor_data.pos != old_pos {
+                            let cursor_data = &mut self.application_data.cursor_data;
+                            if cursor_data.mode != CursorMode::Hidden {
+                                let old_pos = cursor_data.pos;
+                                let confined_pos = cursor_data.confined_position(position);
+                                if confined_pos != position && !cursor_data.software_confined {
+                                    let gl_window = self.display.gl_window();
+                                    if gl_window.window().set_cursor_position(confined_pos).is_err() {
+                                        cursor_data.software_confined = true;
+                                    }
+                                }
+                                cursor_data.pos = confined_pos;
+                                if cursor_data.pos != old_pos {
                                     self.emit_event(Event::CursorMoved);
                                 }
                             }
                         },
+                        WindowEvent::MouseInput { state, button, .. } => {
+                            let position = self.cursor_overlay_position();
+                            let pressed = state == ElementState::Pressed;
+                            self.emit_event(Event::MouseButton { button, pressed, position });
+                        },
+                        WindowEvent::MouseWheel { delta, .. } => {
+                            let position = self.cursor_overlay_position();
+                            self.emit_event(Event::MouseWheel { delta, position });
+                        },
                         WindowEvent::CursorEntered { .. } => (),
                         WindowEvent::CursorLeft { .. } => (),
                         _ => eprintln!("{:?}", we), // TODO
@@ -473,76 +1991,144 @@ impl<A: Application> Engine<A> {
                 }
             },
             WinitEvent::DeviceEvent { event, device_id } => {
-                self.controls.process(device_id, event);
+                // raw motion also arrives while other windows have the focus
+                let motion = match event {
+                    DeviceEvent::MouseMotion { delta } if self.focused => Some(delta),
+                    _ => None,
+                };
+                if self.focused {
+                    if let Some(input) = self.application_data.controls_data.capture(device_id, &event) {
+                        // the controls stay paused while this event is processed
+                        self.emit_event(Event::InputCaptured(input));
+                    }
+                }
+                self.application_data.controls_data.controls.process(device_id, event);
+                if let Some(delta) = motion {
+                    match self.application_data.cursor_data.mode {
+                        CursorMode::Captured | CursorMode::Hidden => {
+                            self.emit_event(Event::MouseMotion { delta });
+                        },
+                        _ => (),
+                    }
+                }
             },
             WinitEvent::UserEvent(_) => (), // TODO
-            WinitEvent::NewEvents(_) => (),
-            WinitEvent::EventsCleared => (),
+            WinitEvent::NewEvents(_) => self.application_data.start_event_batch(),
+            WinitEvent::EventsCleared => {
+                if let Some(input) = self.application_data.controls_data.finish_axis_capture() {
+                    self.emit_event(Event::InputCaptured(input));
+                }
+            },
             WinitEvent::LoopDestroyed => (),
             WinitEvent::Suspended => (),
             WinitEvent::Resumed => (),
         }
     }
 
+    fn cursor_overlay_position(&self) -> Vector2<f64> {
+        self.application_data.cursor_data.overlay_position(OverlayAlignment::Center)
+    }
+
+    // control events are only emitted while the window has the focus,
+    // no text is typed and no input is captured
+    fn update_controls_paused(&mut self) {
+        let paused = !self.focused
+            || self.application_data.text_input
+            || self.application_data.controls_data.capturing;
+        self.application_data.controls_data.set_paused(paused);
+    }
+
     fn maybe_update_game(&mut self) -> Option<Instant> {
-        if let Some(ref mut gd) = self.game_data {
-            if gd.maybe_update() {
-                let next_update_time = gd.next_update_time();
-                self.emit_event(Event::GameUpdated);
+        let gl_window = self.display.gl_window();
+        let next_render_time = self.graphics_data.render_timing.next_render_time();
+        loop {
+            let next_update_time = self.application_data.maybe_update_game(
+                self.clock.now(),
+                Some(gl_window.window()),
+            );
+            // a game in turbo mode keeps updating until the next frame is due
+            if !self.application_data.game_turbo()
+                || self.application_data.closing
+                || self.clock.now() >= next_render_time {
                 return next_update_time;
             }
-            return gd.next_update_time();
         }
-        return None;
     }
 
     fn maybe_render(&mut self) -> Instant {
-        self.graphics_data.maybe_render(
-            &self.application,
+        let now = self.clock.now();
+        let result = self.graphics_data.maybe_render(
+            &self.application_data.application,
             &self.display,
-            self.game_data.as_ref().map(|gd| gd.game_info())
+            &self.clock,
+            self.application_data.game_data.as_ref().map(|gd| gd.game_info(now)),
+            self.application_data.window_data.state.uncapped_rendering,
+            self.application_data.cursor_data.visible_sprite(),
         );
-        return self.graphics_data.next_render_time();
+        if let Err(error) = result {
+            self.emit_event(Event::RenderFailed(error));
+        }
+        return self.graphics_data.render_timing.next_render_time();
     }
 }
 
-fn next_tick_time(ref_time: Instant, num_ticks: u64, tick_rate: u32) -> Instant {
-    ref_time + Duration::from_secs(num_ticks) / tick_rate
+fn next_tick_time(ref_time: Instant, num_ticks: u64, tick_rate: u32) -> Instant {
+    ref_time + Duration::from_secs(num_ticks) / tick_rate
+}
+
+fn window_to_ndc(position: LogicalPosition, window_size: LogicalSize) -> Vector2<f64> {
+    Vector2::new(
+        2.0 * position.x / window_size.width.max(1.0) - 1.0,
+        1.0 - 2.0 * position.y / window_size.height.max(1.0),
+    )
+}
+
+fn ndc_to_window(position: Vector2<f64>, window_size: LogicalSize) -> LogicalPosition {
+    LogicalPosition::new(
+        (position.x + 1.0) / 2.0 * window_size.width.max(1.0),
+        (1.0 - position.y) / 2.0 * window_size.height.max(1.0),
+    )
+}
+
+fn assert_valid_update_rate(update_rate: u32) {
+    assert!(update_rate > 0, "update_rate must be greater than 0");
+}
+
+fn assert_valid_time_scale(time_scale: f64) {
+    assert!(
+        time_scale > 0.0 && time_scale.is_finite(),
+        "time_scale must be positive and finite, got {}",
+        time_scale
+    );
 }
 
-pub fn run_application<A: Application + 'static>() -> ! {
+pub fn run_application<A: Application + 'static>() -> Result<Infallible, EngineError> {
     // creating structures
     let event_loop = EventLoop::new();
     let window_builder = glium::glutin::window::WindowBuilder::new()
         .with_inner_size(A::optimal_window_size())
         .with_title(A::title());
     let context = glium::glutin::ContextBuilder::new();
-    let display = Display::new(window_builder, context, &event_loop).unwrap(); // TODO maybe not unwrap
+    let display = Display::new(window_builder, context, &event_loop)?;
+    let window_size = display.gl_window().window().inner_size();
+    let scale_factor = display.gl_window().window().hidpi_factor();
     let mut binds = Vec::new();
-    let mut graphics = Graphics::new(&display, A::optimal_window_size());
-    let mut controls = Controls::new();
+    let mut graphics = Graphics::new(&display, A::optimal_window_size())?;
     let application = A::new(graphics.object_creator(&display), &mut binds);
-    binds.into_iter().for_each(|bind| controls.add_bind(bind));
-    let render_rate = 60;
+    let clock = RealClock;
+    let mut application_data = ApplicationData::new(application, binds);
+    application_data.cursor_data.window_size = window_size;
     let mut engine = Engine {
-        application,
+        application_data,
         display,
-        controls,
-        game_data: None,
+        focused: true,
         graphics_data: GraphicsData {
             graphics,
-            render_ref_time: Instant::now(),
-            num_renders: 0,
-            render_rate,
-            render_phase: Duration::from_secs(0),
-            last_render: Instant::now() - Duration::from_secs(1) / render_rate,
-            fps: 0.0
-        },
-        cursor_data: CursorData {
-            pos: LogicalPosition::new(0.0, 0.0),
-            mode: CursorMode::Normal,
+            render_timing: RenderTiming::new(clock.now(), 60),
+            window_size,
+            scale_factor,
         },
-        closing: false,
+        clock,
     };
 
     // main loop
@@ -552,10 +2138,11 @@ pub fn run_application<A: Application + 'static>() -> ! {
 
         // this must not be in the device event branch of handle_event,
         // because events may also be produced by binding/unbinding
-        engine.controls.get_events(&mut event_buffer);
+        engine.application_data.controls_data.get_events(&mut event_buffer);
         for event in event_buffer.drain(..) {
             engine.emit_event(Event::ControlEvent(event));
         }
+        engine.update_controls_paused();
 
         // update the game
         let next_tick_time = engine.maybe_update_game();
@@ -564,30 +2151,31 @@ pub fn run_application<A: Application + 'static>() -> ! {
         let next_render_time = engine.maybe_render();
 
         // close application
-        if engine.closing {
+        if engine.application_data.closing {
             *control_flow = ControlFlow::Exit;
             return;
         };
 
-        // schedule next loop
-        let next_loop_time = next_tick_time.map_or(next_render_time, |x| x.min(next_render_time));
-        if Instant::now() < next_loop_time {
-            *control_flow = ControlFlow::WaitUntil(next_loop_time);
+        // schedule next loop, a game in turbo mode continues updating right away
+        let next_loop_time = if engine.application_data.game_turbo() {
+            engine.clock.now()
         } else {
-            // ControlFlow::Poll seems to skip fetching window events
-            //*control_flow = ControlFlow::Poll;
-            *control_flow = ControlFlow::WaitUntil(next_loop_time);
-            println!("polling");
-        }
-    });
+            next_tick_time.map_or(next_render_time, |x| x.min(next_render_time))
+        };
+        // ControlFlow::Poll seems to skip fetching window events, so a due loop waits until a past time
+        *control_flow = ControlFlow::WaitUntil(next_loop_time);
+    })
 }
 
 
 #[cfg(test)]
 mod tests {
-    //use std::time::Instant;
-    //use std::time::Duration;
+    use std::time::Instant;
+    use std::time::Duration;
     use std::cell::Cell;
+    use std::io;
+    use std::io::Read;
+    use std::io::Write;
     use std::io::Cursor;
     use std::rc::Rc;
 
@@ -596,22 +2184,39 @@ mod tests {
 
     use crate::Application;
     use crate::CursorController;
+    use crate::ExternalError;
     use crate::ApplicationController;
     use crate::VirtualKeyCode;
     use crate::GameController;
     use crate::GraphicsInfo;
+    use crate::WindowState;
     use crate::run_application;
+    use crate::HeadlessRunner;
+    use crate::RenderTiming;
+    use crate::CursorData;
+    use crate::ControlsData;
+    use crate::CursorMode;
+    use glium::glutin::dpi::LogicalPosition;
+    use glium::glutin::event::DeviceEvent;
+    use glium::glutin::event::DeviceId;
+    use glium::glutin::event::KeyboardInput;
+    use glium::glutin::event::ElementState;
     use crate::GameStatus;
     use crate::GameInfo;
     use crate::FireTrigger;
     use crate::HoldableTrigger;
     use crate::ControlBind;
     use crate::ControlEvent;
+    use crate::CapturedInput;
+    use crate::TextEditKey;
     use crate::Game;
+    use crate::Interpolate;
     use crate::Event;
     use crate::LogicalSize;
     use crate::Color;
     use crate::SceneObjectCreator;
+    use crate::SceneObjectCreationError;
+    use crate::graphics::create::validate_indices;
     use crate::SceneRenderer;
     use crate::TexturelessSceneObject2d;
     use crate::TexturedSceneObject2d;
@@ -627,9 +2232,14 @@ mod tests {
     use crate::Texture2d;
     use crate::TEXT_NUM_LINES;
     use crate::OverlayAlignment;
+    use crate::Camera;
+    use crate::Projection;
+    use crate::graphics::pick::intersect_triangles;
+    use crate::Recording;
+    use crate::Savable;
 
     const NUM_TICKS: u64 = 131;
-    //const TICK_RATE: u32 = 50;
+    const TICK_RATE: u32 = 50;
 
     #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, ToString, EnumString)]
     enum FireTarget {
@@ -639,6 +2249,11 @@ mod tests {
         FreeCursor,
         CaptureCursor,
         HideCursor,
+        Spin,
+        ToggleFullscreen,
+        RebindSpin,
+        StepGame,
+        StartTyping,
     }
 
     #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, ToString, EnumString)]
@@ -655,21 +2270,65 @@ mod tests {
         }
     }
 
+    #[derive(Debug, PartialEq)]
+    enum TestGameEvent {
+        Milestone(u64),
+    }
+
     #[derive(Clone)]
     struct TestGame {
         cube_rotation: f32,
         num_updates: u64,
     }
 
+    impl Interpolate for TestGame {
+        fn interpolate(&self, other: &Self, t: f32) -> Self {
+            TestGame {
+                cube_rotation: self.cube_rotation * (1.0 - t) + other.cube_rotation * t,
+                num_updates: other.num_updates,
+            }
+        }
+    }
+
+    impl Savable for TestGame {
+        fn save(&self, writer: &mut dyn Write) -> io::Result<()> {
+            writer.write_all(&self.cube_rotation.to_bits().to_le_bytes())?;
+            writer.write_all(&self.num_updates.to_le_bytes())
+        }
+
+        fn load(reader: &mut dyn Read) -> io::Result<Self> {
+            let mut rotation = [0; 4];
+            let mut num_updates = [0; 8];
+            reader.read_exact(&mut rotation)?;
+            reader.read_exact(&mut num_updates)?;
+            Ok(TestGame {
+                cube_rotation: f32::from_bits(u32::from_le_bytes(rotation)),
+                num_updates: u64::from_le_bytes(num_updates),
+            })
+        }
+    }
+
     impl Game for TestGame {
-        fn update(&mut self) -> GameStatus {
-            self.cube_rotation += 0.05;
+        type Command = f32; // Additional rotation
+        type Event = TestGameEvent;
+
+        fn update(&mut self, commands: &[f32], events: &mut Vec<TestGameEvent>) -> GameStatus {
+            self.cube_rotation += 0.05 + commands.iter().sum::<f32>();
             self.num_updates += 1;
-            //if self.num_updates >= NUM_TICKS {
-            //    GameStatus::Ended
-            //} else {
+            if self.num_updates % 50 == 0 {
+                events.push(TestGameEvent::Milestone(self.num_updates));
+            }
+            if self.num_updates >= NUM_TICKS {
+                GameStatus::Ended
+            } else {
                 GameStatus::Running
-            //}
+            }
+        }
+    }
+
+    fn report_cursor_error<C>(error: Option<(C, ExternalError)>) {
+        if let Some((_, error)) = error {
+            eprintln!("cursor mode change failed: {}", error);
         }
     }
 
@@ -707,6 +2366,11 @@ mod tests {
             binds.push(ControlBind::Fire(FireTrigger::Holdable(HoldableTrigger::KeyCode(VirtualKeyCode::F)), FireTarget::FreeCursor));
             binds.push(ControlBind::Fire(FireTrigger::Holdable(HoldableTrigger::KeyCode(VirtualKeyCode::C)), FireTarget::CaptureCursor));
             binds.push(ControlBind::Fire(FireTrigger::Holdable(HoldableTrigger::KeyCode(VirtualKeyCode::H)), FireTarget::HideCursor));
+            binds.push(ControlBind::Fire(FireTrigger::Holdable(HoldableTrigger::KeyCode(VirtualKeyCode::S)), FireTarget::Spin));
+            binds.push(ControlBind::Fire(FireTrigger::Holdable(HoldableTrigger::KeyCode(VirtualKeyCode::F11)), FireTarget::ToggleFullscreen));
+            binds.push(ControlBind::Fire(FireTrigger::Holdable(HoldableTrigger::KeyCode(VirtualKeyCode::R)), FireTarget::RebindSpin));
+            binds.push(ControlBind::Fire(FireTrigger::Holdable(HoldableTrigger::KeyCode(VirtualKeyCode::N)), FireTarget::StepGame));
+            binds.push(ControlBind::Fire(FireTrigger::Holdable(HoldableTrigger::KeyCode(VirtualKeyCode::T)), FireTarget::StartTyping));
 
             let textured_cube_vertices = [
                 (Vector3::new(-0.5, -0.5,  0.5), Vector2::new(0.0, 0.0)),
@@ -820,24 +2484,26 @@ mod tests {
 
         fn handle_event(
             &mut self,
-            event: Event<FireTarget, SwitchTarget, ValueTarget>,
-            controller: ApplicationController<Self>,
+            event: Event<FireTarget, SwitchTarget, ValueTarget, TestGameEvent>,
+            mut controller: ApplicationController<Self>,
         ) {
             match event {
                 Event::ControlEvent(ce) => match ce {
                     ControlEvent::Fire(FireTarget::StartGame) => {
                         if let GameController::Closed(c) = controller.game_controller {
-                            c.start_new(TestGame {
+                            let mut c = c.start_new(TestGame {
                                 cube_rotation: 0.0,
                                 num_updates: 0,
                             });
+                            c.keep_previous_game();
                         }
                     },
                     ControlEvent::Fire(FireTarget::EndGame) => {
-                        if let GameController::Running(c) = controller.game_controller {
-                            c.close();
-                        } else if let GameController::Paused(c) = controller.game_controller {
-                            c.close();
+                        match controller.game_controller {
+                            GameController::Running(c) => { c.close(); },
+                            GameController::Paused(c) => { c.close(); },
+                            GameController::Ended(c) => { c.close(); },
+                            GameController::Closed(_) => (),
                         }
                     },
                     ControlEvent::Fire(FireTarget::ToggleGamePause) => {
@@ -849,33 +2515,70 @@ mod tests {
                     },
                     ControlEvent::Fire(FireTarget::FreeCursor) => {
                         match controller.cursor_controller {
-                            CursorController::Captured(cc) => { cc.free(); },
-                            CursorController::Hidden(cc) => { cc.show(); },
+                            CursorController::Captured(cc) => report_cursor_error(cc.free().err()),
+                            CursorController::Hidden(cc) => report_cursor_error(cc.show().err()),
+                            CursorController::Confined(cc) => report_cursor_error(cc.free().err()),
                             _ => (),
                         }
                     },
                     ControlEvent::Fire(FireTarget::CaptureCursor) => {
                         match controller.cursor_controller {
-                            CursorController::Free(cc) => { cc.capture(); },
-                            CursorController::Hidden(cc) => { cc.show_captured(); },
+                            CursorController::Free(cc) => report_cursor_error(cc.capture().err()),
+                            CursorController::Hidden(cc) => report_cursor_error(cc.show_captured().err()),
                             _ => (),
                         }
                     },
                     ControlEvent::Fire(FireTarget::HideCursor) => {
                         match controller.cursor_controller {
-                            CursorController::Free(cc) => { cc.hide(); },
-                            CursorController::Captured(cc) => { cc.hide(); },
+                            CursorController::Free(cc) => report_cursor_error(cc.hide().err()),
+                            CursorController::Captured(cc) => report_cursor_error(cc.hide().err()),
                             _ => (),
                         }
                     },
+                    ControlEvent::Fire(FireTarget::Spin) => {
+                        if let GameController::Running(mut c) = controller.game_controller {
+                            c.send(1.0);
+                        }
+                    },
+                    ControlEvent::Fire(FireTarget::ToggleFullscreen) => {
+                        let mut window_controller = controller.window_controller;
+                        let fullscreen = window_controller.state().fullscreen;
+                        window_controller.set_fullscreen(!fullscreen);
+                    },
+                    ControlEvent::Fire(FireTarget::RebindSpin) => {
+                        let mut controls_controller = controller.controls_controller;
+                        controls_controller.start_capture();
+                        eprintln!("press a key to spin with");
+                    },
+                    ControlEvent::Fire(FireTarget::StepGame) => {
+                        if let GameController::Paused(mut c) = controller.game_controller {
+                            c.step_forward();
+                        }
+                    },
+                    ControlEvent::Fire(FireTarget::StartTyping) => {
+                        controller.set_text_input(true);
+                        eprintln!("typing, escape to stop");
+                    },
                     ControlEvent::Switch { .. } => (),
                     ControlEvent::Value { .. } => (),
                 },
+                Event::GameEvent(TestGameEvent::Milestone(n)) => {
+                    eprintln!("{} updates", n);
+                },
                 Event::GameUpdated => (),
+                Event::GameUpdatesSkipped(n) => {
+                    eprintln!("skipped {} updates", n);
+                },
+                Event::GameStarted => eprintln!("game started"),
+                Event::GamePaused => eprintln!("game paused"),
+                Event::GameResumed => eprintln!("game resumed"),
+                Event::GameEnded => eprintln!("game ended"),
+                Event::GameClosed => eprintln!("game closed"),
                 Event::CursorMoved => {
                     let position = match controller.cursor_controller {
                         CursorController::Free(cc) => Some(cc.position()),
                         CursorController::Captured(cc) => Some(cc.position()),
+                        CursorController::Confined(cc) => Some(cc.position()),
                         CursorController::Hidden(_) => None,
                     };
                     if let Some(p) = position {
@@ -886,6 +2589,38 @@ mod tests {
                     eprintln!("focus: {}", focus);
                 },
                 Event::CloseRequested => controller.close(),
+                Event::RenderFailed(error) => {
+                    eprintln!("rendering failed: {}", error);
+                    controller.close();
+                },
+                Event::WindowStateChanged(state) => eprintln!("window state changed: {:?}", state),
+                Event::WindowResized(size) => eprintln!("window resized: {:?}", size),
+                Event::ScaleFactorChanged(scale_factor) => eprintln!("scale factor changed: {}", scale_factor),
+                Event::TextInput(c) => eprintln!("text input: {}", c),
+                Event::TextEditKey(TextEditKey::Escape) => {
+                    controller.set_text_input(false);
+                },
+                Event::TextEditKey(key) => eprintln!("text edit key: {:?}", key),
+                Event::MouseButton { button, pressed, position } => {
+                    eprintln!("mouse button {:?} pressed: {} at {:?}", button, pressed, position);
+                },
+                Event::MouseWheel { delta, position } => eprintln!("mouse wheel {:?} at {:?}", delta, position),
+                Event::MouseMotion { .. } => (),
+                Event::InputCaptured(CapturedInput::Key(key_code)) => {
+                    let mut controls_controller = controller.controls_controller;
+                    let spin_bind = controls_controller.binds().iter().position(|bind| match bind {
+                        ControlBind::Fire(_, FireTarget::Spin) => true,
+                        _ => false,
+                    });
+                    let bind = ControlBind::Fire(FireTrigger::Holdable(HoldableTrigger::KeyCode(key_code)), FireTarget::Spin);
+                    match spin_bind {
+                        Some(index) => { controls_controller.replace_bind(index, bind); },
+                        None => controls_controller.add_bind(bind),
+                    }
+                    eprintln!("spinning with {:?}", key_code);
+                },
+                Event::InputCaptured(input) => eprintln!("can't spin with {:?}", input),
+                Event::ReplayFailed(error) => eprintln!("replay failed: {}", error),
             }
         }
 
@@ -897,7 +2632,9 @@ mod tests {
         ) {
             let mut object_renderer = renderer.start_object_rendering(&Default::default());
             let mut overlay_renderer;
-            if let Some(GameInfo { game, paused: _, ended: false }) = game_info {
+            if let Some(game_info) = game_info.filter(|gi| !gi.ended) {
+                let interpolated_game = game_info.interpolated();
+                let game = interpolated_game.as_ref().unwrap_or(game_info.game);
                 object_renderer.draw_textured(
                     &self.textured_cube,
                     &Matrix4::from_angle_z(Rad(game.cube_rotation)),
@@ -997,33 +2734,828 @@ mod tests {
         }
     }
 
+    // Application that only manages the game, so it can run without a window
+    #[derive(Default)]
+    struct HeadlessTestApplication {
+        num_game_updates: u64,
+        num_skipped_updates: u64,
+        game_events: Vec<(u64, TestGameEvent)>,
+        lifecycle_events: Vec<&'static str>,
+        window_states: Vec<WindowState>,
+        record: bool,
+        replay: Option<Recording>,
+        typed: String,
+    }
+
+    impl Application for HeadlessTestApplication {
+        type FireTarget = FireTarget;
+        type SwitchTarget = SwitchTarget;
+        type ValueTarget = ValueTarget;
+        type G = TestGame;
+
+        fn title() -> &'static str {
+            "Headless Test Game"
+        }
+
+        fn optimal_window_size() -> LogicalSize {
+            LogicalSize::new(160.0, 90.0)
+        }
+
+        fn new(
+            _scene_object_creator: SceneObjectCreator,
+            _binds: &mut Vec<ControlBind<FireTarget, SwitchTarget, ValueTarget>>,
+        ) -> Self {
+            Default::default()
+        }
+
+        fn handle_event(
+            &mut self,
+            event: Event<FireTarget, SwitchTarget, ValueTarget, TestGameEvent>,
+            mut controller: ApplicationController<Self>,
+        ) {
+            match event {
+                Event::ControlEvent(ControlEvent::Fire(FireTarget::StartGame)) => {
+                    if let GameController::Closed(c) = controller.game_controller {
+                        let game = TestGame {
+                            cube_rotation: 0.0,
+                            num_updates: 0,
+                        };
+                        if let Some(ref recording) = self.replay {
+                            c.start_replay(game, Default::default(), recording).unwrap();
+                        } else {
+                            let mut c = c.start_new(game);
+                            if self.record {
+                                c.start_recording();
+                            }
+                        }
+                    }
+                },
+                Event::ControlEvent(ControlEvent::Fire(FireTarget::Spin)) => {
+                    if let GameController::Running(mut c) = controller.game_controller {
+                        c.send(0.1);
+                    }
+                },
+                Event::ControlEvent(ControlEvent::Fire(FireTarget::ToggleGamePause)) => {
+                    if let GameController::Running(c) = controller.game_controller {
+                        c.pause();
+                    } else if let GameController::Paused(c) = controller.game_controller {
+                        c.resume();
+                    }
+                },
+                Event::ControlEvent(ControlEvent::Fire(FireTarget::StepGame)) => {
+                    if let GameController::Paused(mut c) = controller.game_controller {
+                        c.step_forward();
+                    }
+                },
+                Event::ControlEvent(ControlEvent::Fire(FireTarget::StartTyping)) => {
+                    controller.set_text_input(true);
+                },
+                Event::TextInput(c) => self.typed.push(c),
+                Event::GameEvent(ge) => self.game_events.push((self.num_game_updates, ge)),
+                Event::GameUpdated => self.num_game_updates += 1,
+                Event::ControlEvent(ControlEvent::Fire(FireTarget::EndGame)) => {
+                    match controller.game_controller {
+                        GameController::Running(c) => { c.close(); },
+                        GameController::Paused(c) => { c.close(); },
+                        GameController::Ended(c) => { c.close(); },
+                        GameController::Closed(_) => (),
+                    }
+                },
+                Event::GameUpdatesSkipped(n) => self.num_skipped_updates += n,
+                Event::GameStarted => self.lifecycle_events.push("started"),
+                Event::GamePaused => self.lifecycle_events.push("paused"),
+                Event::GameResumed => self.lifecycle_events.push("resumed"),
+                Event::GameEnded => self.lifecycle_events.push("ended"),
+                Event::GameClosed => self.lifecycle_events.push("closed"),
+                Event::ReplayFailed(_) => self.lifecycle_events.push("replay failed"),
+                Event::ControlEvent(ControlEvent::Fire(FireTarget::ToggleFullscreen)) => {
+                    let mut window_controller = controller.window_controller;
+                    let fullscreen = window_controller.state().fullscreen;
+                    window_controller.set_fullscreen(!fullscreen);
+                    window_controller.set_title(if fullscreen { "windowed" } else { "fullscreen" });
+                },
+                Event::WindowStateChanged(state) => self.window_states.push(state),
+                Event::CloseRequested => controller.close(),
+                _ => (),
+            }
+        }
+
+        fn render(
+            &self,
+            _game_info: Option<GameInfo<TestGame>>,
+            _graphics_info: GraphicsInfo,
+            _renderer: SceneRenderer
+        ) {
+        }
+    }
+
+    fn fire(target: FireTarget) -> Event<FireTarget, SwitchTarget, ValueTarget, TestGameEvent> {
+        Event::ControlEvent(ControlEvent::Fire(target))
+    }
+
+    // a runner whose game was just started
+    fn started_runner() -> HeadlessRunner<HeadlessTestApplication> {
+        let mut runner = HeadlessRunner::new(HeadlessTestApplication::default());
+        runner.emit_event(fire(FireTarget::StartGame));
+        runner
+    }
+
     #[test]
-    fn test_all() {
-        // TODO move the checks into run
-        //let start_time = Instant::now();
-        run_application::<TestApplication>();
-        /*let duration = Instant::now() - start_time;
+    fn test_headless() {
+        let mut runner = started_runner();
+        let start_time = runner.now();
+        while !runner.game_info().unwrap().ended {
+            runner.advance(Duration::from_millis(1));
+        }
+        let duration = runner.now() - start_time;
 
-        assert_eq!(app.game.num_ticks, NUM_TICKS);
-        assert!(
-            app.game.num_renders.get() >= NUM_TICKS,
-            "left: {}, right: {}",
-            app.game.num_renders.get(),
-            NUM_TICKS
-        );
+        assert_eq!(runner.game_info().unwrap().game.num_updates, NUM_TICKS);
+        assert_eq!(runner.application().num_game_updates, NUM_TICKS);
 
         const NUM_MILLIS: u64 = (NUM_TICKS - 1) * 1000 / TICK_RATE as u64;
         const TARGET_DURATION: Duration = Duration::from_millis(NUM_MILLIS);
-        let diff = if duration > TARGET_DURATION {
-            duration - TARGET_DURATION
-        } else {
-            TARGET_DURATION - duration
+        assert_eq!(duration, TARGET_DURATION, "run duration differs");
+    }
+
+    #[test]
+    fn test_headless_pause() {
+        let runner = HeadlessRunner::new(HeadlessTestApplication::default());
+        let game = runner.run(vec![
+            (Duration::from_millis(0), fire(FireTarget::StartGame)),
+            (Duration::from_millis(490), fire(FireTarget::ToggleGamePause)),
+            (Duration::from_secs(5), fire(FireTarget::ToggleGamePause)),
+            (Duration::from_millis(510), Event::CloseRequested),
+        ]).unwrap();
+
+        // one second of running time, the first update happens immediately
+        assert_eq!(game.num_updates, TICK_RATE as u64 + 1);
+    }
+
+    #[test]
+    fn test_pause_accounting() {
+        let mut runner = started_runner();
+        let start_time = runner.now();
+        runner.advance(Duration::from_micros(12_345));
+        runner.emit_event(fire(FireTarget::ToggleGamePause));
+        assert_eq!(runner.next_update_time(), None);
+        runner.advance(Duration::from_micros(777_777));
+        runner.emit_event(fire(FireTarget::ToggleGamePause));
+
+        // the second update is delayed by exactly the pause
+        let expected = start_time + Duration::from_millis(20) + Duration::from_micros(777_777);
+        assert_eq!(runner.next_update_time(), Some(expected));
+        assert_eq!(runner.application().num_game_updates, 1);
+    }
+
+    #[test]
+    fn test_update_rate_change() {
+        let mut runner = started_runner();
+        let start_time = runner.now();
+        runner.advance(Duration::from_millis(30));
+        assert_eq!(runner.application().num_game_updates, 2);
+
+        // the next update follows the last one with the new timing
+        if let GameController::Running(mut c) = runner.game_controller() {
+            c.set_update_rate(10);
+        }
+        assert_eq!(runner.next_update_time(), Some(start_time + Duration::from_millis(120)));
+        if let GameController::Running(mut c) = runner.game_controller() {
+            c.set_time_scale(4.0);
+        }
+        assert_eq!(runner.next_update_time(), Some(start_time + Duration::from_millis(45)));
+
+        runner.advance(Duration::from_millis(30));
+        assert_eq!(runner.application().num_game_updates, 3);
+        assert_eq!(runner.next_update_time(), Some(start_time + Duration::from_millis(70)));
+    }
+
+    #[test]
+    fn test_catch_up() {
+        let mut runner = started_runner();
+
+        // simulate a frame that took one second
+        runner.clock().advance(Duration::from_secs(1));
+        runner.advance(Duration::from_secs(0));
+
+        // five updates to catch up, then the remaining due updates but one are dropped
+        assert_eq!(runner.application().num_game_updates, 6);
+        assert_eq!(runner.application().num_skipped_updates, 45);
+        assert_eq!(runner.next_update_time(), Some(runner.now() + Duration::from_millis(20)));
+    }
+
+    #[test]
+    fn test_update_progress() {
+        let mut runner = started_runner();
+        if let GameController::Running(mut c) = runner.game_controller() {
+            c.keep_previous_game();
+        }
+        runner.advance(Duration::from_millis(25));
+        let game_info = runner.game_info().unwrap();
+        assert!((game_info.update_progress - 0.25).abs() < 1e-6);
+        assert_eq!(game_info.previous_game.unwrap().num_updates, 1);
+        let interpolated = game_info.interpolated().unwrap();
+        assert!((interpolated.cube_rotation - 0.0625).abs() < 1e-6);
+
+        // the progress stays where it was while the game is paused
+        runner.emit_event(fire(FireTarget::ToggleGamePause));
+        runner.advance(Duration::from_millis(10));
+        assert!((runner.game_info().unwrap().update_progress - 0.25).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_commands() {
+        let mut runner = started_runner();
+        runner.advance(Duration::from_millis(5));
+        if let GameController::Running(mut c) = runner.game_controller() {
+            c.send(0.5);
+            c.send(0.25);
+        }
+        assert!((runner.game_info().unwrap().game.cube_rotation - 0.05).abs() < 1e-6);
+
+        // the commands are applied with the next update only
+        runner.advance(Duration::from_millis(20));
+        assert!((runner.game_info().unwrap().game.cube_rotation - 0.85).abs() < 1e-6);
+        runner.advance(Duration::from_millis(20));
+        assert!((runner.game_info().unwrap().game.cube_rotation - 0.9).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_game_events() {
+        let mut runner = started_runner();
+        runner.advance(Duration::from_millis(2010));
+
+        // each event arrives before the update it was emitted in is reported
+        assert_eq!(runner.application().num_game_updates, 101);
+        assert_eq!(runner.application().game_events, vec![
+            (49, TestGameEvent::Milestone(50)),
+            (99, TestGameEvent::Milestone(100)),
+        ]);
+    }
+
+    #[test]
+    fn test_lifecycle_events() {
+        let mut runner = started_runner();
+        runner.emit_event(fire(FireTarget::ToggleGamePause));
+        runner.emit_event(fire(FireTarget::ToggleGamePause));
+        runner.advance(Duration::from_secs(3));
+        runner.emit_event(fire(FireTarget::EndGame));
+        runner.emit_event(fire(FireTarget::StartGame));
+
+        // transitions through the game controller outside of events are reported as well
+        if let GameController::Running(c) = runner.game_controller() {
+            c.close();
+        }
+        runner.advance(Duration::from_millis(1));
+
+        assert_eq!(runner.application().lifecycle_events, vec![
+            "started", "paused", "resumed", "ended", "closed", "started", "closed",
+        ]);
+    }
+
+    #[test]
+    fn test_record_replay() {
+        let mut runner = HeadlessRunner::new(HeadlessTestApplication {
+            record: true,
+            ..Default::default()
+        });
+        runner.emit_event(fire(FireTarget::StartGame));
+        runner.advance(Duration::from_millis(95));
+        runner.emit_event(fire(FireTarget::Spin));
+        runner.advance(Duration::from_millis(333));
+        runner.emit_event(fire(FireTarget::Spin));
+        runner.emit_event(fire(FireTarget::ToggleGamePause));
+        runner.advance(Duration::from_millis(77));
+        runner.emit_event(fire(FireTarget::ToggleGamePause));
+        runner.emit_event(fire(FireTarget::Spin));
+        runner.advance(Duration::from_millis(500));
+        let recording = match runner.game_controller() {
+            GameController::Running(mut c) => c.take_recording().unwrap(),
+            _ => panic!("game is not running"),
         };
-        assert!(
-            diff < Duration::from_millis(20),
-            "run duration differs: {:?} != {:?}",
-            duration,
-            TARGET_DURATION
-        );*/
+        let game = runner.game_info().unwrap().game.clone();
+
+        let mut buffer = Vec::new();
+        recording.write_to(&mut buffer).unwrap();
+        let recording = Recording::read_from(&buffer[..]).unwrap();
+        assert_eq!(recording.num_ticks(), game.num_updates);
+
+        let mut runner = HeadlessRunner::new(HeadlessTestApplication {
+            replay: Some(recording),
+            ..Default::default()
+        });
+        runner.emit_event(fire(FireTarget::StartGame));
+
+        // live input is dropped while the replay runs
+        runner.advance(Duration::from_millis(45));
+        runner.emit_event(fire(FireTarget::Spin));
+        runner.emit_event(fire(FireTarget::ToggleGamePause));
+        runner.advance(Duration::from_millis(20));
+        match runner.game_controller() {
+            GameController::Running(_) => (),
+            _ => panic!("game is not running"),
+        }
+        while runner.game_info().unwrap().game.num_updates < game.num_updates {
+            runner.advance(Duration::from_millis(1));
+        }
+        let replayed_game = runner.game_info().unwrap().game;
+        assert_eq!(replayed_game.num_updates, game.num_updates);
+        assert_eq!(replayed_game.cube_rotation.to_bits(), game.cube_rotation.to_bits());
+        assert_eq!(runner.application().lifecycle_events, vec!["started", "paused", "resumed"]);
+    }
+
+    #[test]
+    fn test_invalid_replay() {
+        let recording = "ungamed-recording 1\nticks 10\n2 control fire Spin\n4 control fire Dance\n";
+        let mut runner = HeadlessRunner::new(HeadlessTestApplication {
+            replay: Some(Recording::read_from(recording.as_bytes()).unwrap()),
+            ..Default::default()
+        });
+        runner.emit_event(fire(FireTarget::StartGame));
+        runner.advance(Duration::from_millis(100));
+
+        // the replay stops at the unknown event and live input is taken again
+        assert_eq!(runner.application().lifecycle_events, vec!["started", "replay failed"]);
+        runner.emit_event(fire(FireTarget::Spin));
+        runner.advance(Duration::from_millis(20));
+        let expected_rotation = 7.0 * 0.05 + 0.1;
+        assert!((runner.game_info().unwrap().game.cube_rotation - expected_rotation).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_record_step_replay() {
+        let mut runner = HeadlessRunner::new(HeadlessTestApplication {
+            record: true,
+            ..Default::default()
+        });
+        runner.emit_event(fire(FireTarget::StartGame));
+        runner.advance(Duration::from_millis(95));
+        runner.emit_event(fire(FireTarget::ToggleGamePause));
+        runner.advance(Duration::from_millis(30));
+        runner.emit_event(fire(FireTarget::StepGame));
+        runner.advance(Duration::from_millis(30));
+        runner.emit_event(fire(FireTarget::StepGame));
+        runner.emit_event(fire(FireTarget::ToggleGamePause));
+        runner.emit_event(fire(FireTarget::Spin));
+        runner.advance(Duration::from_millis(200));
+        let recording = match runner.game_controller() {
+            GameController::Running(mut c) => c.take_recording().unwrap(),
+            _ => panic!("game is not running"),
+        };
+        let game = runner.game_info().unwrap().game.clone();
+
+        // the resume is recorded two ticks after the pause, the steps get the replay there
+        let mut runner = HeadlessRunner::new(HeadlessTestApplication {
+            replay: Some(recording),
+            ..Default::default()
+        });
+        runner.emit_event(fire(FireTarget::StartGame));
+        while runner.game_info().unwrap().game.num_updates < game.num_updates {
+            runner.advance(Duration::from_millis(1));
+        }
+        let replayed_game = runner.game_info().unwrap().game;
+        assert_eq!(replayed_game.num_updates, game.num_updates);
+        assert_eq!(replayed_game.cube_rotation.to_bits(), game.cube_rotation.to_bits());
+        assert_eq!(runner.application().lifecycle_events, vec!["started", "paused", "resumed"]);
+    }
+
+    #[test]
+    fn test_save_load() {
+        let mut runner = started_runner();
+        if let GameController::Running(mut c) = runner.game_controller() {
+            c.set_update_rate(25);
+        }
+        runner.advance(Duration::from_millis(390));
+        let mut buffer = Vec::new();
+        match runner.game_controller() {
+            GameController::Running(c) => c.save(&mut buffer).unwrap(),
+            _ => panic!("game is not running"),
+        }
+        let game = runner.game_info().unwrap().game.clone();
+        assert_eq!(game.num_updates, 10);
+
+        let mut runner = HeadlessRunner::new(HeadlessTestApplication::default());
+        runner.advance(Duration::from_millis(123));
+        match runner.game_controller() {
+            GameController::Closed(c) => assert_eq!(c.load(&buffer[..]).unwrap().update_rate(), 25),
+            _ => panic!("game is not closed"),
+        }
+        let loaded_game = runner.game_info().unwrap().game;
+        assert_eq!(loaded_game.num_updates, game.num_updates);
+        assert_eq!(loaded_game.cube_rotation.to_bits(), game.cube_rotation.to_bits());
+        assert_eq!(runner.next_update_time(), Some(runner.now() + Duration::from_millis(40)));
+
+        // a broken save leaves the game as it was
+        match runner.game_controller() {
+            GameController::Running(mut c) => assert!(c.load(&buffer[..20]).is_err()),
+            _ => panic!("game is not running"),
+        }
+        assert_eq!(runner.game_info().unwrap().game.num_updates, game.num_updates);
+    }
+
+    #[test]
+    fn test_rewind() {
+        let mut runner = started_runner();
+        if let GameController::Running(mut c) = runner.game_controller() {
+            c.keep_rewind_buffer(10, 2);
+        }
+        runner.advance(Duration::from_millis(200));
+        runner.emit_event(fire(FireTarget::Spin));
+        runner.advance(Duration::from_millis(380));
+        runner.emit_event(fire(FireTarget::ToggleGamePause));
+        let game = runner.game_info().unwrap().game.clone();
+        assert_eq!(game.num_updates, 30);
+
+        let mut expected_game = TestGame { cube_rotation: 0.0, num_updates: 0 };
+        for tick in 0..12 {
+            let commands = if tick == 11 { vec![0.1] } else { vec![] };
+            expected_game.update(&commands, &mut Vec::new());
+        }
+        match runner.game_controller() {
+            GameController::Paused(mut c) => {
+                // only the snapshots of tick 10 and 20 are kept
+                assert!(!c.rewind_to(5));
+                assert!(c.rewind_to(12));
+            },
+            _ => panic!("game is not paused"),
+        }
+        let rewound_game = runner.game_info().unwrap().game;
+        assert_eq!(rewound_game.num_updates, 12);
+        assert_eq!(rewound_game.cube_rotation.to_bits(), expected_game.cube_rotation.to_bits());
+
+        if let GameController::Paused(mut c) = runner.game_controller() {
+            for _ in 12..30 {
+                c.step_forward();
+            }
+        }
+        let stepped_game = runner.game_info().unwrap().game;
+        assert_eq!(stepped_game.num_updates, game.num_updates);
+        assert_eq!(stepped_game.cube_rotation.to_bits(), game.cube_rotation.to_bits());
+    }
+
+    #[test]
+    fn test_rewind_recording() {
+        let mut runner = HeadlessRunner::new(HeadlessTestApplication {
+            record: true,
+            ..Default::default()
+        });
+        runner.emit_event(fire(FireTarget::StartGame));
+        if let GameController::Running(mut c) = runner.game_controller() {
+            c.keep_rewind_buffer(10, 2);
+        }
+        runner.advance(Duration::from_millis(390));
+        runner.emit_event(fire(FireTarget::ToggleGamePause));
+        match runner.game_controller() {
+            GameController::Paused(mut c) => {
+                assert!(c.rewind_to(10));
+                assert_eq!(c.take_recording().unwrap().num_ticks(), 20);
+                assert!(c.take_recording().is_none());
+            },
+            _ => panic!("game is not paused"),
+        }
+    }
+
+    #[test]
+    fn test_step() {
+        let mut runner = started_runner();
+        runner.advance(Duration::from_millis(90));
+        runner.emit_event(fire(FireTarget::ToggleGamePause));
+        if let GameController::Paused(mut c) = runner.game_controller() {
+            c.step(3);
+        }
+        runner.advance(Duration::from_millis(100));
+        let game_info = runner.game_info().unwrap();
+        assert_eq!(game_info.game.num_updates, 8);
+        assert_eq!(game_info.tick, 8);
+        assert_eq!(game_info.game_time, Duration::from_millis(160));
+        assert_eq!(game_info.update_rate, 50);
+        assert_eq!(runner.application().num_game_updates, 8);
+        assert_eq!(runner.next_update_time(), None);
+
+        // the update that was due when the game was paused comes next
+        runner.emit_event(fire(FireTarget::ToggleGamePause));
+        assert_eq!(runner.next_update_time(), Some(runner.now() + Duration::from_millis(10)));
+        runner.advance(Duration::from_millis(10));
+        assert_eq!(runner.game_info().unwrap().game.num_updates, 9);
+        assert_eq!(runner.next_update_time(), Some(runner.now() + Duration::from_millis(20)));
+    }
+
+    #[test]
+    fn test_step_to_end_load() {
+        let mut runner = started_runner();
+        runner.advance(Duration::from_millis(2560));
+        let mut buffer = Vec::new();
+        match runner.game_controller() {
+            GameController::Running(c) => c.save(&mut buffer).unwrap(),
+            _ => panic!("game is not running"),
+        }
+        runner.emit_event(fire(FireTarget::ToggleGamePause));
+        if let GameController::Paused(mut c) = runner.game_controller() {
+            c.step(5);
+        }
+        assert_eq!(runner.game_info().unwrap().game.num_updates, NUM_TICKS);
+        assert!(!runner.game_info().unwrap().paused);
+
+        // the game that was stepped to its end isn't paused after loading
+        match runner.game_controller() {
+            GameController::Ended(c) => { c.load(&buffer[..]).unwrap(); },
+            _ => panic!("game has not ended"),
+        }
+        match runner.game_controller() {
+            GameController::Running(_) => (),
+            _ => panic!("game is not running"),
+        }
+        assert_eq!(runner.next_update_time(), Some(runner.now() + Duration::from_millis(20)));
+        runner.advance(Duration::from_millis(20));
+        assert_eq!(runner.game_info().unwrap().game.num_updates, NUM_TICKS - 1);
+        assert_eq!(runner.application().lifecycle_events, vec!["started", "paused", "ended", "started"]);
+    }
+
+    #[test]
+    fn test_step_sent_commands() {
+        let mut runner = started_runner();
+        if let GameController::Running(mut c) = runner.game_controller() {
+            c.keep_rewind_buffer(10, 5);
+        }
+        runner.advance(Duration::from_millis(30));
+        if let GameController::Running(mut c) = runner.game_controller() {
+            c.send(0.5);
+            c.pause().step(1);
+        }
+
+        // the step at the newest tick takes the commands that were sent before the pause
+        let game = runner.game_info().unwrap().game;
+        assert_eq!(game.num_updates, 3);
+        assert!((game.cube_rotation - 0.65).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_turbo() {
+        let mut runner = started_runner();
+        if let GameController::Running(mut c) = runner.game_controller() {
+            c.set_turbo(true);
+        }
+        assert!(runner.game_info().unwrap().turbo);
+        runner.advance(Duration::from_millis(1));
+        runner.advance(Duration::from_millis(1));
+        assert_eq!(runner.game_info().unwrap().game.num_updates, 10);
+
+        // back to the update rate without catching up
+        runner.advance(Duration::from_millis(500));
+        if let GameController::Running(mut c) = runner.game_controller() {
+            c.set_turbo(false);
+        }
+        assert!(!runner.game_info().unwrap().turbo);
+        let num_updates = runner.game_info().unwrap().game.num_updates;
+        runner.advance(Duration::from_millis(19));
+        assert_eq!(runner.game_info().unwrap().game.num_updates, num_updates);
+        runner.advance(Duration::from_millis(1));
+        assert_eq!(runner.game_info().unwrap().game.num_updates, num_updates + 1);
+        assert_eq!(runner.application().num_skipped_updates, 0);
+    }
+
+    #[test]
+    fn test_text_input() {
+        let mut runner = HeadlessRunner::new(HeadlessTestApplication::default());
+        runner.receive_character('a');
+        runner.emit_event(fire(FireTarget::StartTyping));
+        runner.receive_character('t');
+        runner.receive_character('h');
+        runner.receive_character('\r');
+        runner.advance(Duration::from_millis(10));
+        runner.receive_character('i');
+        assert_eq!(runner.application().typed, "hi");
+
+        // a key press without a character doesn't swallow the first one of the next batch
+        let mut runner = HeadlessRunner::new(HeadlessTestApplication::default());
+        runner.emit_event(fire(FireTarget::StartTyping));
+        runner.advance(Duration::from_millis(10));
+        runner.receive_character('h');
+        assert_eq!(runner.application().typed, "h");
+    }
+
+    #[test]
+    fn test_validate_indices() {
+        assert!(validate_indices(&[0, 1, 2, 2, 1, 3], 4).is_ok());
+        match validate_indices(&[0, 1, 2, 2, 1], 4) {
+            Err(SceneObjectCreationError::IncompleteTriangle { num_indices: 5 }) => (),
+            result => panic!("unexpected result: {:?}", result),
+        }
+        match validate_indices(&[0, 1, 2, 2, 1, 4], 4) {
+            Err(SceneObjectCreationError::IndexOutOfRange { index: 4, num_vertices: 4 }) => (),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_window_state_events() {
+        let mut runner = HeadlessRunner::new(HeadlessTestApplication::default());
+        runner.emit_event(fire(FireTarget::ToggleFullscreen));
+        runner.emit_event(fire(FireTarget::ToggleFullscreen));
+
+        // both changes of one event are reported together
+        let states = &runner.application().window_states;
+        assert_eq!(states.len(), 2);
+        assert!(states[0].fullscreen);
+        assert_eq!(states[0].title, "fullscreen");
+        assert!(!states[1].fullscreen);
+        assert_eq!(states[1].title, "windowed");
+        assert_eq!(states[1].size, HeadlessTestApplication::optimal_window_size());
+    }
+
+    #[test]
+    fn test_rebinding() {
+        let spin_bind = |key_code| {
+            ControlBind::Fire(FireTrigger::Holdable(HoldableTrigger::KeyCode(key_code)), FireTarget::Spin)
+        };
+        let mut runner = HeadlessRunner::new(HeadlessTestApplication::default());
+        let mut controls_controller = runner.controls_controller();
+        assert!(controls_controller.binds().is_empty());
+        controls_controller.add_bind(spin_bind(VirtualKeyCode::S));
+        controls_controller.add_bind(ControlBind::Fire(
+            FireTrigger::Holdable(HoldableTrigger::KeyCode(VirtualKeyCode::P)),
+            FireTarget::ToggleGamePause,
+        ));
+        controls_controller.replace_bind(0, spin_bind(VirtualKeyCode::D));
+        controls_controller.remove_bind(1);
+
+        let binds = controls_controller.binds();
+        assert_eq!(binds.len(), 1);
+        match binds[0] {
+            ControlBind::Fire(FireTrigger::Holdable(HoldableTrigger::KeyCode(key_code)), FireTarget::Spin) => {
+                assert_eq!(key_code, VirtualKeyCode::D);
+            },
+            _ => panic!("the spin bind was not replaced"),
+        }
+
+        controls_controller.start_capture();
+        assert!(controls_controller.capturing());
+        controls_controller.cancel_capture();
+        assert!(!controls_controller.capturing());
+    }
+
+    fn key_event(key_code: VirtualKeyCode, state: ElementState) -> DeviceEvent {
+        DeviceEvent::Key(KeyboardInput {
+            scancode: 0,
+            state,
+            virtual_keycode: Some(key_code),
+            modifiers: Default::default(),
+        })
+    }
+
+    #[test]
+    fn test_captured_input() {
+        let key_press = key_event(VirtualKeyCode::S, ElementState::Pressed);
+        let key_release = key_event(VirtualKeyCode::S, ElementState::Released);
+        let button_press = DeviceEvent::Button { button: 3, state: ElementState::Pressed };
+        let stick_motion = DeviceEvent::Motion { axis: 1, value: -0.8 };
+        let stick_wobble = DeviceEvent::Motion { axis: 1, value: 0.1 };
+        assert_eq!(CapturedInput::from_device_event(&key_press), Some(CapturedInput::Key(VirtualKeyCode::S)));
+        assert_eq!(CapturedInput::from_device_event(&key_release), None);
+        assert_eq!(CapturedInput::from_device_event(&button_press), Some(CapturedInput::Button(3)));
+        assert_eq!(CapturedInput::from_device_event(&stick_motion), Some(CapturedInput::Axis { axis: 1, value: -0.8 }));
+        assert_eq!(CapturedInput::from_device_event(&stick_wobble), None);
+    }
+
+    #[test]
+    fn test_capture_filter() {
+        let device_id = unsafe { DeviceId::dummy() };
+        let mut controls_data = ControlsData::<HeadlessTestApplication>::new(Vec::new());
+        let key_press = key_event(VirtualKeyCode::S, ElementState::Pressed);
+        assert_eq!(controls_data.capture(device_id, &key_press), None);
+
+        controls_data.capturing = true;
+        assert_eq!(controls_data.capture(device_id, &key_press), Some(CapturedInput::Key(VirtualKeyCode::S)));
+        assert!(!controls_data.capturing);
+
+        controls_data.capturing = true;
+        let button_press = DeviceEvent::Button { button: 3, state: ElementState::Pressed };
+        assert_eq!(controls_data.capture(device_id, &button_press), Some(CapturedInput::Button(3)));
+        assert!(!controls_data.capturing);
+
+        // a joystick axis is captured once the events are handled
+        controls_data.capturing = true;
+        let stick_motion = DeviceEvent::Motion { axis: 1, value: -0.8 };
+        assert_eq!(controls_data.capture(device_id, &stick_motion), None);
+        assert!(controls_data.capturing);
+        assert_eq!(controls_data.finish_axis_capture(), Some(CapturedInput::Axis { axis: 1, value: -0.8 }));
+        assert!(!controls_data.capturing);
+
+        // the axis motion of a mouse is followed by its mouse motion
+        controls_data.capturing = true;
+        assert_eq!(controls_data.capture(device_id, &DeviceEvent::Motion { axis: 0, value: 12.0 }), None);
+        assert_eq!(controls_data.capture(device_id, &DeviceEvent::MouseMotion { delta: (12.0, 0.0) }), None);
+        assert_eq!(controls_data.finish_axis_capture(), None);
+        assert!(controls_data.capturing);
+
+        // a key press during the axis motion is captured instead of the axis
+        assert_eq!(controls_data.capture(device_id, &stick_motion), None);
+        assert_eq!(controls_data.capture(device_id, &key_press), Some(CapturedInput::Key(VirtualKeyCode::S)));
+        assert_eq!(controls_data.finish_axis_capture(), None);
+    }
+
+    #[test]
+    fn test_cursor_coordinates() {
+        // the overlay covers the middle half of a window that is twice as wide as the optimal one
+        let cursor_data = CursorData {
+            pos: LogicalPosition::new(800.0, 300.0),
+            mode: CursorMode::Normal,
+            window_size: LogicalSize::new(1600.0, 600.0),
+            optimal_window_size: LogicalSize::new(800.0, 600.0),
+            sprite: None,
+            software_confined: false,
+        };
+        let assert_close = |a: Vector2<f64>, b: Vector2<f64>| {
+            assert!((a.x - b.x).abs() < 1e-9 && (a.y - b.y).abs() < 1e-9, "{:?} != {:?}", a, b);
+        };
+        assert_close(cursor_data.ndc_position(), Vector2::new(0.0, 0.0));
+        assert_close(cursor_data.overlay_position(OverlayAlignment::Center), Vector2::new(400.0, 300.0));
+        assert_close(cursor_data.overlay_position(OverlayAlignment::TopLeft), Vector2::new(800.0, 300.0));
+        assert_close(cursor_data.overlay_position(OverlayAlignment::Right), Vector2::new(0.0, 300.0));
+
+        let transform = cursor_data.transform();
+        let ndc = transform.overlay_to_ndc(Vector2::new(0.0, 600.0), OverlayAlignment::Center);
+        assert_close(ndc, Vector2::new(-0.5, 1.0));
+        let pos = transform.ndc_to_window(ndc);
+        assert_close(Vector2::new(pos.0, pos.1), Vector2::new(400.0, 0.0));
+        let overlay = transform.window_to_overlay(pos, OverlayAlignment::Center);
+        assert_close(overlay, Vector2::new(0.0, 600.0));
+    }
+
+    #[test]
+    fn test_cursor_modes() {
+        let mut cursor_data = CursorData {
+            pos: LogicalPosition::new(10.0, 20.0),
+            mode: CursorMode::Normal,
+            window_size: LogicalSize::new(800.0, 600.0),
+            optimal_window_size: LogicalSize::new(800.0, 600.0),
+            sprite: None,
+            software_confined: false,
+        };
+        let confined = match CursorController::new(&mut cursor_data, None) {
+            CursorController::Free(cc) => cc.confine((300.0, 100.0), (100.0, 200.0)).ok().unwrap(),
+            _ => panic!("cursor is not free"),
+        };
+        assert_eq!(confined.bounds(), ((100.0, 100.0), (300.0, 200.0)));
+        let clamped = cursor_data.confined_position(LogicalPosition::new(400.0, 150.0));
+        assert_eq!(clamped, LogicalPosition::new(300.0, 150.0));
+
+        match CursorController::new(&mut cursor_data, None) {
+            CursorController::Confined(cc) => { cc.hide().ok().unwrap(); },
+            _ => panic!("cursor is not confined"),
+        }
+        assert!(cursor_data.mode == CursorMode::Hidden);
+    }
+
+    #[test]
+    fn test_picking() {
+        let camera = Camera {
+            projection: Projection::Central { y_fov: 1.0, near: 0.5, far: 100.0 },
+            translation_rotation: Matrix4::from_translation(Vector3::new(0.0, 0.0, -5.0)),
+        };
+        let ray = camera.ray(Vector2::new(0.0, 0.0), 2.0, 1.0).unwrap();
+        assert!(ray.origin.x.abs() < 1e-4 && ray.origin.y.abs() < 1e-4);
+        assert!((ray.origin.z - 4.5).abs() < 1e-4);
+        assert!((ray.direction.z + 1.0).abs() < 1e-4);
+
+        // a square around the origin, facing the camera
+        let positions = [
+            Vector3::new(-1.0, -1.0, 0.0),
+            Vector3::new(1.0, -1.0, 0.0),
+            Vector3::new(1.0, 1.0, 0.0),
+            Vector3::new(-1.0, 1.0, 0.0),
+        ];
+        let indices = [0, 1, 2, 0, 2, 3];
+        let distance = intersect_triangles(&ray, &positions, &indices).unwrap();
+        assert!((distance - 4.5).abs() < 1e-4);
+
+        // the edge of the screen is cropped to the optimal ratio, so it points past the square
+        let ray = camera.ray(Vector2::new(1.0, 0.0), 2.0, 1.0).unwrap();
+        assert!(intersect_triangles(&ray, &positions, &indices).is_none());
+    }
+
+    #[test]
+    fn test_fps_smoothing() {
+        let start_time = Instant::now();
+        let mut timing = RenderTiming::new(start_time, 60);
+        for i in 0..1000 {
+            let render_time = start_time + Duration::from_micros(i * 25_000);
+            timing.render_finished(render_time, render_time + Duration::from_micros(2_000));
+        }
+
+        // rendering every 25ms converges to 40 fps
+        assert!((timing.fps - 40.0).abs() < 0.01, "fps: {}", timing.fps);
+        assert_eq!(timing.render_phase, Duration::from_secs(0));
+
+        // a slow render shifts the following renders
+        let render_time = start_time + Duration::from_secs(25);
+        timing.render_finished(render_time, render_time + Duration::from_micros(12_500));
+        assert_eq!(timing.render_phase, Duration::from_micros(9_500));
+    }
+
+    // opens a window and runs until it's closed, run it with --ignored on a desktop
+    #[test]
+    #[ignore]
+    fn test_all() {
+        run_application::<TestApplication>().unwrap();
     }
 }
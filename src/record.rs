@@ -0,0 +1,215 @@
+use std::io;
+use std::io::Write;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::fs::File;
+use std::path::Path;
+use std::str::FromStr;
+use std::collections::vec_deque::VecDeque;
+
+use crate::Game;
+use crate::ControlEvent;
+use crate::SwitchState;
+
+const HEADER: &str = "ungamed-recording 1";
+
+#[derive(Clone, Debug, PartialEq)]
+enum RecordedInput {
+    Control(String),
+    Command(String),
+}
+
+// Control events and game commands of a game session, stored with the tick they applied to.
+// Ticks are counted from the start of the recording.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Recording {
+    num_ticks: u64,
+    inputs: Vec<(u64, RecordedInput)>,
+}
+
+impl Recording {
+    pub fn num_ticks(&self) -> u64 {
+        self.num_ticks
+    }
+
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "{}", HEADER)?;
+        writeln!(writer, "ticks {}", self.num_ticks)?;
+        for (tick, input) in &self.inputs {
+            match input {
+                RecordedInput::Control(event) => writeln!(writer, "{} control {}", tick, event)?,
+                RecordedInput::Command(command) => writeln!(writer, "{} command {}", tick, command)?,
+            }
+        }
+        writer.flush()
+    }
+
+    pub fn read_from<R: BufRead>(reader: R) -> io::Result<Self> {
+        let mut lines = reader.lines();
+        if lines.next().transpose()?.as_ref().map(|l| l.as_str()) != Some(HEADER) {
+            return Err(invalid_data("missing recording header"));
+        }
+        let num_ticks_line = lines.next().transpose()?.unwrap_or_default();
+        let mut parts = num_ticks_line.splitn(2, ' ');
+        let num_ticks = match (parts.next(), parts.next().and_then(|n| n.parse().ok())) {
+            (Some("ticks"), Some(num_ticks)) => num_ticks,
+            _ => return Err(invalid_data("missing number of ticks")),
+        };
+
+        let mut inputs = Vec::new();
+        for line in lines {
+            let line = line?;
+            let mut parts = line.splitn(3, ' ');
+            let tick = match parts.next().and_then(|t| t.parse().ok()) {
+                Some(tick) if tick <= num_ticks => tick,
+                _ => return Err(invalid_data("invalid tick")),
+            };
+            let content = parts.next().and_then(|kind| parts.next().map(|c| (kind, c.to_string())));
+            let input = match content {
+                Some(("control", event)) => RecordedInput::Control(event),
+                Some(("command", command)) => RecordedInput::Command(command),
+                _ => return Err(invalid_data("invalid input")),
+            };
+            inputs.push((tick, input));
+        }
+        Ok(Recording { num_ticks, inputs })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write_to(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Recording::read_from(BufReader::new(File::open(path)?))
+    }
+}
+
+pub(crate) struct Recorder<G: Game> {
+    start_tick: u64,
+    recording: Recording,
+    command_to_string: fn(&G::Command) -> String,
+}
+
+impl<G: Game> Recorder<G> {
+    pub(crate) fn new(start_tick: u64) -> Self where G::Command: ToString {
+        Recorder {
+            start_tick,
+            recording: Default::default(),
+            command_to_string: <G::Command as ToString>::to_string,
+        }
+    }
+
+    pub(crate) fn record_control_event(&mut self, tick: u64, event: String) {
+        let tick = tick - self.start_tick;
+        self.recording.inputs.push((tick, RecordedInput::Control(event)));
+    }
+
+    pub(crate) fn record_commands(&mut self, tick: u64, commands: &[G::Command]) {
+        let tick = tick - self.start_tick;
+        for command in commands {
+            let command = (self.command_to_string)(command);
+            self.recording.inputs.push((tick, RecordedInput::Command(command)));
+        }
+    }
+
+    pub(crate) fn finish(mut self, tick: u64) -> Recording {
+        self.recording.num_ticks = tick - self.start_tick;
+        self.recording
+    }
+}
+
+pub(crate) struct Replay<G: Game> {
+    end_tick: u64,
+    control_events: VecDeque<(u64, String)>,
+    commands: VecDeque<(u64, G::Command)>,
+}
+
+impl<G: Game> Replay<G> {
+    pub(crate) fn new(recording: &Recording, start_tick: u64) -> io::Result<Self>
+    where G::Command: FromStr {
+        let mut control_events = VecDeque::new();
+        let mut commands = VecDeque::new();
+        for (tick, input) in &recording.inputs {
+            let tick = start_tick + tick;
+            match input {
+                RecordedInput::Control(event) => control_events.push_back((tick, event.clone())),
+                RecordedInput::Command(command) => match command.parse() {
+                    Ok(command) => commands.push_back((tick, command)),
+                    Err(_) => return Err(invalid_data("invalid command")),
+                },
+            }
+        }
+        Ok(Replay {
+            end_tick: start_tick + recording.num_ticks,
+            control_events,
+            commands,
+        })
+    }
+
+    pub(crate) fn finished(&self, tick: u64) -> bool {
+        tick >= self.end_tick
+    }
+
+    pub(crate) fn take_control_events(&mut self, tick: u64) -> Vec<String> {
+        let mut events = Vec::new();
+        while self.control_events.front().map_or(false, |(t, _)| *t <= tick) {
+            events.push(self.control_events.pop_front().unwrap().1);
+        }
+        events
+    }
+
+    // replaces the commands with the recorded ones
+    pub(crate) fn take_commands(&mut self, tick: u64, commands: &mut Vec<G::Command>) {
+        commands.clear();
+        while self.commands.front().map_or(false, |(t, _)| *t <= tick) {
+            commands.push(self.commands.pop_front().unwrap().1);
+        }
+    }
+}
+
+pub(crate) fn control_event_to_string<F, S, V>(event: &ControlEvent<F, S, V>) -> String
+where F: ToString, S: ToString, V: ToString {
+    match event {
+        ControlEvent::Fire(target) => format!("fire {}", target.to_string()),
+        ControlEvent::Switch { target, state } => {
+            let state = match state {
+                SwitchState::Active => "active",
+                SwitchState::Inactive => "inactive",
+            };
+            format!("switch {} {}", target.to_string(), state)
+        },
+        ControlEvent::Value { target, value } => format!("value {} {}", target.to_string(), value),
+    }
+}
+
+pub(crate) fn parse_control_event<F, S, V>(event: &str) -> Option<ControlEvent<F, S, V>>
+where F: FromStr, S: FromStr, V: FromStr {
+    let mut parts = event.splitn(2, ' ');
+    let kind = parts.next()?;
+    let content = parts.next()?;
+    match kind {
+        "fire" => content.parse().ok().map(ControlEvent::Fire),
+        "switch" => {
+            let mut parts = content.rsplitn(2, ' ');
+            let state = match parts.next()? {
+                "active" => SwitchState::Active,
+                "inactive" => SwitchState::Inactive,
+                _ => return None,
+            };
+            let target = parts.next()?.parse().ok()?;
+            Some(ControlEvent::Switch { target, state })
+        },
+        "value" => {
+            let mut parts = content.rsplitn(2, ' ');
+            let value = parts.next()?.parse().ok()?;
+            let target = parts.next()?.parse().ok()?;
+            Some(ControlEvent::Value { target, value })
+        },
+        _ => None,
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
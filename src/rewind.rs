@@ -0,0 +1,93 @@
+use std::time::Duration;
+use std::collections::vec_deque::VecDeque;
+
+use crate::Game;
+
+// Snapshots of a game every few ticks, and the commands of every tick since the oldest one
+pub(crate) struct RewindBuffer<G: Game> {
+    interval: u64,
+    capacity: usize,
+    clone_game: fn(&G) -> G,
+    clone_command: fn(&G::Command) -> G::Command,
+    snapshots: VecDeque<(u64, Duration, G)>,
+    commands: VecDeque<(u64, Vec<G::Command>)>,
+    end_tick: u64,
+}
+
+impl<G: Game> RewindBuffer<G> {
+    pub(crate) fn new(interval: u64, capacity: usize) -> Self where G: Clone, G::Command: Clone {
+        assert!(interval > 0, "interval must be greater than 0");
+        assert!(capacity > 0, "capacity must be greater than 0");
+        RewindBuffer {
+            interval,
+            capacity,
+            clone_game: G::clone,
+            clone_command: <G::Command as Clone>::clone,
+            snapshots: VecDeque::new(),
+            commands: VecDeque::new(),
+            end_tick: 0,
+        }
+    }
+
+    // called before the update of the tick, ticks that are already buffered are kept as they are
+    pub(crate) fn record(
+        &mut self,
+        tick: u64,
+        game_time: Duration,
+        game: &G,
+        commands: &[G::Command],
+    ) {
+        if tick < self.end_tick {
+            return;
+        }
+        if tick % self.interval == 0 {
+            self.snapshots.push_back((tick, game_time, (self.clone_game)(game)));
+            if self.snapshots.len() > self.capacity {
+                self.snapshots.pop_front();
+                let oldest_tick = self.snapshots.front().unwrap().0;
+                while self.commands.front().map_or(false, |(t, _)| *t < oldest_tick) {
+                    self.commands.pop_front();
+                }
+            }
+        }
+        if !self.snapshots.is_empty() && !commands.is_empty() {
+            let commands = commands.iter().map(self.clone_command).collect();
+            self.commands.push_back((tick, commands));
+        }
+        self.end_tick = tick + 1;
+    }
+
+    // the latest snapshot at or before the tick, if the tick is in the buffered range
+    pub(crate) fn snapshot(&self, tick: u64) -> Option<(u64, Duration, G)> {
+        if tick > self.end_tick {
+            return None;
+        }
+        self.snapshots.iter()
+            .rev()
+            .find(|(t, _, _)| *t <= tick)
+            .map(|(t, game_time, game)| (*t, *game_time, (self.clone_game)(game)))
+    }
+
+    // whether the tick was updated before, so its commands are buffered
+    pub(crate) fn recorded(&self, tick: u64) -> bool {
+        tick < self.end_tick
+    }
+
+    pub(crate) fn commands(&self, tick: u64, commands: &mut Vec<G::Command>) {
+        commands.clear();
+        if let Some((_, buffered)) = self.commands.iter().find(|(t, _)| *t == tick) {
+            commands.extend(buffered.iter().map(self.clone_command));
+        }
+    }
+
+    // forgets everything from the tick on, so the game can take a different course
+    pub(crate) fn truncate(&mut self, tick: u64) {
+        while self.snapshots.back().map_or(false, |(t, _, _)| *t >= tick) {
+            self.snapshots.pop_back();
+        }
+        while self.commands.back().map_or(false, |(t, _)| *t >= tick) {
+            self.commands.pop_back();
+        }
+        self.end_tick = self.end_tick.min(tick);
+    }
+}
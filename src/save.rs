@@ -0,0 +1,71 @@
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::time::Duration;
+
+const HEADER: &str = "ungamed-save 1";
+const MAX_HEADER_LINE_LENGTH: usize = 64;
+
+// Games that implement this can be saved and loaded through the game controllers
+pub trait Savable: Sized {
+    fn save(&self, writer: &mut dyn Write) -> io::Result<()>;
+    fn load(reader: &mut dyn Read) -> io::Result<Self>;
+}
+
+pub(crate) struct SaveHeader {
+    pub(crate) tick: u64,
+    pub(crate) game_time: Duration,
+    pub(crate) update_rate: u32,
+}
+
+impl SaveHeader {
+    pub(crate) fn write(&self, writer: &mut dyn Write) -> io::Result<()> {
+        writeln!(writer, "{}", HEADER)?;
+        writeln!(writer, "tick {}", self.tick)?;
+        writeln!(writer, "game_time {}", self.game_time.as_nanos())?;
+        writeln!(writer, "update_rate {}", self.update_rate)
+    }
+
+    pub(crate) fn read(reader: &mut dyn Read) -> io::Result<Self> {
+        if read_line(reader)? != HEADER {
+            return Err(invalid_data("missing save header"));
+        }
+        let tick = read_value(reader, "tick")?;
+        let game_time = Duration::from_nanos(read_value(reader, "game_time")?);
+        let update_rate = read_value(reader, "update_rate")?;
+        if update_rate == 0 {
+            return Err(invalid_data("update_rate must be greater than 0"));
+        }
+        Ok(SaveHeader { tick, game_time, update_rate })
+    }
+}
+
+fn read_value<T: std::str::FromStr>(reader: &mut dyn Read, name: &str) -> io::Result<T> {
+    let line = read_line(reader)?;
+    let mut parts = line.splitn(2, ' ');
+    match (parts.next(), parts.next().and_then(|v| v.parse().ok())) {
+        (Some(n), Some(value)) if n == name => Ok(value),
+        _ => Err(invalid_data(&format!("missing {}", name))),
+    }
+}
+
+// reads byte by byte, so nothing after the line is consumed
+fn read_line(reader: &mut dyn Read) -> io::Result<String> {
+    let mut line = Vec::new();
+    let mut byte = [0];
+    loop {
+        reader.read_exact(&mut byte)?;
+        if byte[0] == b'\n' {
+            break;
+        }
+        if line.len() >= MAX_HEADER_LINE_LENGTH {
+            return Err(invalid_data("header line too long"));
+        }
+        line.push(byte[0]);
+    }
+    String::from_utf8(line).map_err(|_| invalid_data("header is not valid UTF-8"))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}